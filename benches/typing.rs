@@ -0,0 +1,47 @@
+// Per-keystroke cost early on and near the end of a long text. The numbers for both positions
+// should be about the same; if the late ones grow with the text, something rescans it.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use typing_racer::{Layout, TextManager};
+
+fn long_text() -> String {
+  include_str!("../texts/Bulgarian/chitanka2").repeat(4)
+}
+
+// A manager with the first `position` chars typed, every tenth one wrong.
+fn typed_up_to(text: &str, position: usize) -> TextManager {
+  let mut manager = TextManager::new(String::from(text)).unwrap();
+  for (i, c) in text.chars().take(position).enumerate() {
+    manager.type_char(if i % 10 == 9 { '#' } else { c });
+  }
+  manager
+}
+
+fn keystrokes(c: &mut Criterion) {
+  let text = long_text();
+  let length = text.chars().count();
+  let mut group = c.benchmark_group("keystroke");
+  for (name, position) in [("early", 5_000), ("late", length - 10)] {
+    let mut manager = typed_up_to(&text, position);
+    let next = text.chars().nth(position).unwrap();
+
+    group.bench_function(BenchmarkId::new("type_and_delete", name), |b| {
+      b.iter(|| {
+        manager.type_char(next);
+        manager.del_char();
+      })
+    });
+    // What drawing a 40x80 text window needs on every key, once the text is laid out.
+    let layout = Layout::new(&text, 80);
+    group.bench_function(BenchmarkId::new("visible_parts", name), |b| {
+      b.iter(|| {
+        let first = layout.line_at(manager.get_cursor()).saturating_sub(20);
+        manager.get_text_parts_from(layout.lines()[first].start).len()
+      })
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(benches, keystrokes);
+criterion_main!(benches);
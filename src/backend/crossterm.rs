@@ -0,0 +1,215 @@
+use super::{Backend, Cell, Key, Style, Window};
+use crossterm::style::Color;
+
+fn colors(style: Style) -> (Color, Color) {
+  let text = Color::Rgb { r: 220, g: 220, b: 220 };
+  let background = Color::Rgb { r: 24, g: 24, b: 24 };
+  match style {
+    Style::Normal => (text, background),
+    Style::Right => (Color::Rgb { r: 110, g: 200, b: 120 }, background),
+    Style::Wrong => (text, Color::Rgb { r: 190, g: 40, b: 50 }),
+    Style::Selected | Style::CurrentChar => (background, text),
+    Style::Underlined => (text, background),
+    Style::Keyword => (Color::Rgb { r: 200, g: 120, b: 220 }, background),
+    Style::String => (Color::Rgb { r: 230, g: 190, b: 120 }, background),
+    Style::Comment => (Color::Rgb { r: 110, g: 130, b: 150 }, background),
+    Style::Number => (Color::Rgb { r: 90, g: 190, b: 200 }, background),
+  }
+}
+
+pub struct CrosstermBackend {
+  out: std::io::Stdout,
+  // What is currently on the terminal, so that only changed cells are written.
+  screen: std::vec::Vec<Option<Cell>>,
+  size: (i32, i32),
+  cursor: Option<(i32, i32)>,
+  // Whether the terminal reports modifiers on keys like Backspace, which has to be undone.
+  enhanced_keys: bool,
+}
+
+impl CrosstermBackend {
+  pub fn new() -> typing_racer::Result<CrosstermBackend> {
+    use typing_racer::Error;
+    let mut out = std::io::stdout();
+    crossterm::terminal::enable_raw_mode().map_err(Error::io("set up the terminal"))?;
+    crossterm::execute!(
+      out,
+      crossterm::terminal::EnterAlternateScreen,
+      crossterm::cursor::Hide
+    )
+    .map_err(Error::io("set up the terminal"))?;
+    // Without this, Ctrl+Backspace cannot be told from Backspace.
+    let enhanced_keys = crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false)
+      && crossterm::execute!(
+        out,
+        crossterm::event::PushKeyboardEnhancementFlags(
+          crossterm::event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        )
+      )
+      .is_ok();
+    let mut backend = CrosstermBackend {
+      out,
+      screen: vec![],
+      size: (0, 0),
+      cursor: None,
+      enhanced_keys,
+    };
+    backend.resize();
+    Ok(backend)
+  }
+
+  fn resize(&mut self) {
+    let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    self.size = (rows as i32, columns as i32);
+    self.screen = vec![None; rows as usize * columns as usize];
+  }
+
+  // Drawing moves the terminal's cursor around, so it is put back after every draw.
+  fn place_cursor(&mut self) {
+    use crossterm::QueueableCommand;
+    let _ = match self.cursor {
+      Some((y, x)) => self
+        .out
+        .queue(crossterm::cursor::MoveTo(x as u16, y as u16))
+        .and_then(|out| out.queue(crossterm::cursor::SetCursorStyle::SteadyBar))
+        .and_then(|out| out.queue(crossterm::cursor::Show))
+        .map(|_| ()),
+      None => self.out.queue(crossterm::cursor::Hide).map(|_| ()),
+    };
+    use std::io::Write;
+    let _ = self.out.flush();
+  }
+
+  fn restore(&mut self) {
+    if self.enhanced_keys {
+      let _ = crossterm::execute!(self.out, crossterm::event::PopKeyboardEnhancementFlags);
+    }
+    let _ = crossterm::execute!(
+      self.out,
+      crossterm::style::ResetColor,
+      crossterm::cursor::SetCursorStyle::DefaultUserShape,
+      crossterm::cursor::Show,
+      crossterm::terminal::LeaveAlternateScreen
+    );
+    let _ = crossterm::terminal::disable_raw_mode();
+  }
+}
+
+impl Backend for CrosstermBackend {
+  fn size(&self) -> (i32, i32) {
+    self.size
+  }
+
+  fn read_key(&mut self, timeout: Option<std::time::Duration>) -> Option<Key> {
+    use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+    if let Some(timeout) = timeout {
+      if !crossterm::event::poll(timeout).unwrap_or(false) {
+        return None;
+      }
+    }
+    match crossterm::event::read().ok()? {
+      Event::Key(event) if event.kind != KeyEventKind::Release => match event.code {
+        // Raw mode turns off the signal, so quit the way curses would on SIGINT.
+        KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+          self.restore();
+          std::process::exit(130);
+        }
+        KeyCode::Char('w') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+          Some(Key::DeleteWord)
+        }
+        // Some terminals send ^H for Backspace, which comes through as Ctrl+H.
+        KeyCode::Char('h') if event.modifiers == KeyModifiers::CONTROL => Some(Key::Backspace),
+        KeyCode::Backspace if event.modifiers.contains(KeyModifiers::CONTROL) => {
+          Some(Key::DeleteWord)
+        }
+        // Other Ctrl shortcuts are not letters to type, unlike AltGr ones, which come with Alt too.
+        KeyCode::Char(_) if event.modifiers == KeyModifiers::CONTROL => None,
+        KeyCode::Char(c) => Some(Key::Char(c)),
+        KeyCode::Enter => Some(Key::Char('\n')),
+        KeyCode::Tab => Some(Key::Char('\t')),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Esc => Some(Key::Escape),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        _ => None,
+      },
+      Event::Resize(..) => {
+        self.resize();
+        Some(Key::Resize)
+      }
+      _ => None,
+    }
+  }
+
+  fn draw(&mut self, window: &Window) {
+    use crossterm::style::{Attribute, Print, SetAttribute, SetColors};
+    use crossterm::QueueableCommand;
+
+    let (y, x) = window.get_beg_yx();
+    let (height, _) = window.get_max_yx();
+    let (rows, columns) = self.size;
+    if y >= rows || x >= columns {
+      return;
+    }
+    let mut style = None;
+    for row in 0..height.min(rows - y) {
+      let mut moved = false;
+      for (column, cell) in window.row(row).iter().enumerate().take((columns - x) as usize) {
+        let index = ((y + row) * columns + x) as usize + column;
+        if self.screen[index].as_ref() == Some(cell) {
+          moved = false;
+          continue;
+        }
+        self.screen[index] = Some(cell.clone());
+        // Drawn along with the wide grapheme before it.
+        if cell.is_wide_tail() {
+          moved = false;
+          continue;
+        }
+        if !moved {
+          let _ = self
+            .out
+            .queue(crossterm::cursor::MoveTo((x as usize + column) as u16, (y + row) as u16));
+          moved = true;
+        }
+        if style != Some(cell.style) {
+          let (foreground, background) = colors(cell.style);
+          let _ = self
+            .out
+            .queue(SetColors(crossterm::style::Colors::new(foreground, background)));
+          let _ = self.out.queue(SetAttribute(if cell.style == Style::Underlined {
+            Attribute::Underlined
+          } else {
+            Attribute::NoUnderline
+          }));
+          style = Some(cell.style);
+        }
+        let _ = self.out.queue(Print(cell.grapheme()));
+      }
+    }
+    self.place_cursor();
+  }
+
+  fn set_cursor(&mut self, position: Option<(i32, i32)>) {
+    if position != self.cursor {
+      self.cursor = position;
+      self.place_cursor();
+    }
+  }
+
+  fn clear(&mut self) {
+    self.screen.iter_mut().for_each(|cell| *cell = None);
+    let _ = crossterm::execute!(
+      self.out,
+      crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+    );
+  }
+}
+
+impl Drop for CrosstermBackend {
+  fn drop(&mut self) {
+    self.restore();
+  }
+}
@@ -0,0 +1,149 @@
+use super::{Backend, Key, Style, Window};
+
+const STYLES: [(Style, i16, i16); 10] = [
+  (Style::Normal, pancurses::COLOR_WHITE, pancurses::COLOR_BLACK),
+  (Style::Right, pancurses::COLOR_GREEN, pancurses::COLOR_BLACK),
+  (Style::Wrong, pancurses::COLOR_WHITE, pancurses::COLOR_RED),
+  (Style::Selected, pancurses::COLOR_BLACK, pancurses::COLOR_WHITE),
+  (Style::CurrentChar, pancurses::COLOR_BLACK, pancurses::COLOR_WHITE),
+  (Style::Underlined, pancurses::COLOR_WHITE, pancurses::COLOR_BLACK),
+  (Style::Keyword, pancurses::COLOR_MAGENTA, pancurses::COLOR_BLACK),
+  (Style::String, pancurses::COLOR_YELLOW, pancurses::COLOR_BLACK),
+  (Style::Comment, pancurses::COLOR_BLUE, pancurses::COLOR_BLACK),
+  (Style::Number, pancurses::COLOR_CYAN, pancurses::COLOR_BLACK),
+];
+
+// Sets the cursor to a steady bar on terminals that support it, which curses has no call for.
+const BAR_CURSOR: &str = "\x1b[6 q";
+const DEFAULT_CURSOR: &str = "\x1b[0 q";
+
+pub struct CursesBackend {
+  window: pancurses::Window,
+  cursor: Option<(i32, i32)>,
+  bar_cursor: bool,
+}
+
+impl CursesBackend {
+  pub fn new() -> CursesBackend {
+    std::env::set_var("ESCDELAY", "0");
+
+    let window = pancurses::initscr();
+
+    window.keypad(true);
+
+    pancurses::noecho();
+    pancurses::start_color();
+    pancurses::nl();
+    pancurses::curs_set(0);
+
+    // Pair 0 is the terminal default and cannot be changed, so pairs start at 1.
+    for (pair, (_, foreground, background)) in STYLES.iter().enumerate() {
+      pancurses::init_pair(pair as i16 + 1, *foreground, *background);
+    }
+
+    CursesBackend {
+      window,
+      cursor: None,
+      bar_cursor: false,
+    }
+  }
+
+  fn write_escape(sequence: &str) {
+    use std::io::Write;
+    let mut out = std::io::stdout();
+    let _ = out.write_all(sequence.as_bytes());
+    let _ = out.flush();
+  }
+
+  fn color_pair(style: Style) -> i16 {
+    STYLES
+      .iter()
+      .position(|(s, _, _)| *s == style)
+      .map_or(0, |pair| pair as i16 + 1)
+  }
+}
+
+impl Backend for CursesBackend {
+  fn size(&self) -> (i32, i32) {
+    self.window.get_max_yx()
+  }
+
+  fn read_key(&mut self, timeout: Option<std::time::Duration>) -> Option<Key> {
+    self
+      .window
+      .timeout(timeout.map_or(-1, |timeout| timeout.as_millis() as i32));
+    match self.window.getch()? {
+      pancurses::Input::Character('\u{1b}') => Some(Key::Escape),
+      // Some terminals send ^H for Backspace.
+      pancurses::Input::Character('\u{7f}')
+      | pancurses::Input::Character('\u{8}')
+      | pancurses::Input::KeyBackspace => Some(Key::Backspace),
+      pancurses::Input::Character('\u{17}') => Some(Key::DeleteWord),
+      pancurses::Input::Character(c) => Some(Key::Char(c)),
+      pancurses::Input::KeyUp => Some(Key::Up),
+      pancurses::Input::KeyDown => Some(Key::Down),
+      pancurses::Input::KeyLeft => Some(Key::Left),
+      pancurses::Input::KeyRight => Some(Key::Right),
+      pancurses::Input::KeyResize => Some(Key::Resize),
+      _ => None,
+    }
+  }
+
+  fn draw(&mut self, window: &Window) {
+    let (y, x) = window.get_beg_yx();
+    let (height, _) = window.get_max_yx();
+    for row in 0..height {
+      self.window.mv(y + row, x);
+      // Drawing a wide grapheme already moves past the cell after it.
+      for cell in window.row(row).iter().filter(|cell| !cell.is_wide_tail()) {
+        self.window.color_set(Self::color_pair(cell.style));
+        if cell.style == Style::Underlined {
+          self.window.attron(pancurses::A_UNDERLINE);
+        }
+        // addch does not handle characters outside of Latin-1.
+        self.window.addstr(cell.grapheme());
+        self.window.attroff(pancurses::A_UNDERLINE);
+      }
+    }
+    // Refreshing leaves the terminal's cursor where the window's is.
+    match self.cursor {
+      Some((y, x)) => {
+        self.window.mv(y, x);
+        pancurses::curs_set(1);
+      }
+      None => {
+        pancurses::curs_set(0);
+      }
+    }
+    self.window.refresh();
+  }
+
+  fn set_cursor(&mut self, position: Option<(i32, i32)>) {
+    if position.is_some() && !self.bar_cursor {
+      Self::write_escape(BAR_CURSOR);
+      self.bar_cursor = true;
+    }
+    if position != self.cursor {
+      self.cursor = position;
+      if let Some((y, x)) = position {
+        self.window.mv(y, x);
+      }
+      pancurses::curs_set(if position.is_some() { 1 } else { 0 });
+      self.window.refresh();
+    }
+  }
+
+  fn clear(&mut self) {
+    self.window.clear();
+    self.window.refresh();
+  }
+}
+
+impl Drop for CursesBackend {
+  fn drop(&mut self) {
+    pancurses::endwin();
+    if self.bar_cursor {
+      Self::write_escape(DEFAULT_CURSOR);
+    }
+  }
+}
@@ -0,0 +1,291 @@
+// The screens in `ui` draw into `Window`s, which are plain cell grids, and hand them to a `Backend`
+// to put on the terminal. Only the backends know about pancurses or crossterm.
+
+use unicode_segmentation::UnicodeSegmentation;
+use typing_racer::{grapheme_width, TAB_STOP};
+
+#[cfg(feature = "crossterm")]
+mod crossterm;
+#[cfg(all(feature = "curses", not(feature = "crossterm")))]
+mod curses;
+#[cfg(not(any(feature = "curses", feature = "crossterm")))]
+compile_error!("the terminal interface needs the `curses` or `crossterm` feature");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+  // Enter and Tab come through as '\n' and '\t'.
+  Char(char),
+  Backspace,
+  // Ctrl+W, or Ctrl+Backspace where the terminal tells it apart from Backspace.
+  DeleteWord,
+  Escape,
+  Up,
+  Down,
+  Left,
+  Right,
+  Resize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Style {
+  #[default]
+  Normal,
+  Right,
+  Wrong,
+  Selected,
+  CurrentChar,
+  // The current char, under an underline caret.
+  Underlined,
+  Keyword,
+  String,
+  Comment,
+  Number,
+}
+
+// Holds one grapheme. A wide grapheme takes two cells, and the second one holds `WIDE_TAIL`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+  pub c: char,
+  // The combining chars and anything else in the grapheme after `c`.
+  pub rest: Option<Box<str>>,
+  pub style: Style,
+}
+
+impl Cell {
+  pub const WIDE_TAIL: char = '\0';
+
+  pub fn is_wide_tail(&self) -> bool {
+    self.c == Cell::WIDE_TAIL
+  }
+
+  pub fn grapheme(&self) -> String {
+    let mut grapheme = String::from(self.c);
+    grapheme.push_str(self.rest.as_deref().unwrap_or_default());
+    grapheme
+  }
+}
+
+const BLANK: Cell = Cell {
+  c: ' ',
+  rest: None,
+  style: Style::Normal,
+};
+
+pub trait Backend {
+  // (rows, columns)
+  fn size(&self) -> (i32, i32);
+
+  // Waits up to `timeout` for a key, or until one comes if there is no timeout.
+  fn read_key(&mut self, timeout: Option<std::time::Duration>) -> Option<Key>;
+
+  // Shows the window's cells at its position on the screen.
+  fn draw(&mut self, window: &Window);
+
+  // Shows the terminal's cursor as a bar at a position on the screen, or hides it.
+  fn set_cursor(&mut self, position: Option<(i32, i32)>);
+
+  // Forgets what is on the screen, so the next draws repaint everything.
+  fn clear(&mut self);
+}
+
+#[cfg(feature = "crossterm")]
+pub fn open() -> typing_racer::Result<Box<dyn Backend>> {
+  Ok(Box::new(self::crossterm::CrosstermBackend::new()?))
+}
+
+#[cfg(all(feature = "curses", not(feature = "crossterm")))]
+pub fn open() -> typing_racer::Result<Box<dyn Backend>> {
+  Ok(Box::new(curses::CursesBackend::new()))
+}
+
+// A rectangle of the screen with a cursor, written to like a curses window.
+#[derive(Default)]
+pub struct Window {
+  y: i32,
+  x: i32,
+  height: i32,
+  width: i32,
+  cells: std::vec::Vec<Cell>,
+  cursor: (i32, i32),
+  style: Style,
+}
+
+impl Window {
+  pub fn new(height: i32, width: i32, y: i32, x: i32) -> Window {
+    let height = height.max(0);
+    let width = width.max(0);
+    Window {
+      y,
+      x,
+      height,
+      width,
+      cells: vec![BLANK; (height * width) as usize],
+      cursor: (0, 0),
+      style: Style::Normal,
+    }
+  }
+
+  pub fn get_beg_yx(&self) -> (i32, i32) {
+    (self.y, self.x)
+  }
+
+  pub fn get_max_yx(&self) -> (i32, i32) {
+    (self.height, self.width)
+  }
+
+  pub fn get_cur_yx(&self) -> (i32, i32) {
+    self.cursor
+  }
+
+  pub fn row(&self, y: i32) -> &[Cell] {
+    let start = (y * self.width) as usize;
+    &self.cells[start..start + self.width as usize]
+  }
+
+  pub fn color_set(&mut self, style: Style) {
+    self.style = style;
+  }
+
+  pub fn mv(&mut self, y: i32, x: i32) {
+    self.cursor = (
+      y.clamp(0, (self.height - 1).max(0)),
+      x.clamp(0, (self.width - 1).max(0)),
+    );
+  }
+
+  pub fn clrtoeol(&mut self) {
+    let (y, x) = self.cursor;
+    if y < self.height {
+      let start = (y * self.width) as usize;
+      let row = &mut self.cells[start..start + self.width as usize];
+      row[x as usize..].iter_mut().for_each(|cell| *cell = BLANK);
+    }
+  }
+
+  pub fn addstr(&mut self, text: impl AsRef<str>) {
+    for grapheme in text.as_ref().graphemes(true) {
+      self.add_grapheme(grapheme);
+    }
+  }
+
+  // Writes as much of `text` as fits in `n` columns.
+  pub fn addnstr(&mut self, text: impl AsRef<str>, n: usize) {
+    let mut columns = 0;
+    for grapheme in text.as_ref().graphemes(true) {
+      columns += grapheme_width(grapheme, columns);
+      if columns > n {
+        break;
+      }
+      self.add_grapheme(grapheme);
+    }
+  }
+
+  pub fn mvaddch(&mut self, y: i32, x: i32, c: char) {
+    self.mv(y, x);
+    self.addch(c);
+  }
+
+  pub fn addch(&mut self, c: char) {
+    self.add_grapheme(c.encode_utf8(&mut [0; 4]));
+  }
+
+  pub fn add_grapheme(&mut self, grapheme: &str) {
+    if self.height == 0 || self.width == 0 {
+      return;
+    }
+    match grapheme {
+      "\n" => {
+        self.clrtoeol();
+        self.new_line();
+      }
+      "\t" => {
+        let spaces = TAB_STOP - self.cursor.1 as usize % TAB_STOP;
+        for _ in 0..spaces {
+          self.addch(' ');
+        }
+      }
+      grapheme => {
+        let width = grapheme_width(grapheme, 0).min(self.width as usize) as i32;
+        // Like terminals, a wide grapheme that does not fit at the end of a row goes on the next.
+        if self.cursor.1 + width > self.width {
+          self.clrtoeol();
+          self.new_line();
+        }
+        let mut chars = grapheme.chars();
+        let c = chars.next().unwrap_or(' ');
+        let rest = Some(chars.as_str()).filter(|rest| !rest.is_empty());
+        let (y, x) = self.cursor;
+        let start = (y * self.width + x) as usize;
+        self.cells[start] = Cell {
+          c,
+          rest: rest.map(Box::from),
+          style: self.style,
+        };
+        for cell in &mut self.cells[start + 1..start + width as usize] {
+          *cell = Cell {
+            c: Cell::WIDE_TAIL,
+            rest: None,
+            style: self.style,
+          };
+        }
+        if x + width < self.width {
+          self.cursor.1 += width;
+        } else {
+          self.new_line();
+        }
+      }
+    }
+  }
+
+  // The cursor stays on the last line, going back to its start.
+  fn new_line(&mut self) {
+    self.cursor = ((self.cursor.0 + 1).min(self.height - 1), 0);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(window: &Window, y: i32) -> String {
+    window.row(y).iter().map(|cell| cell.c).collect()
+  }
+
+  #[test]
+  fn wraps_and_clears() {
+    let mut window = Window::new(3, 4, 0, 0);
+
+    window.addstr("abcdef\nxy");
+    assert_eq!(text(&window, 0), "abcd");
+    assert_eq!(text(&window, 1), "ef  ");
+    assert_eq!(text(&window, 2), "xy  ");
+    assert_eq!(window.get_cur_yx(), (2, 2));
+
+    window.mv(0, 1);
+    window.clrtoeol();
+    assert_eq!(text(&window, 0), "a   ");
+  }
+
+  #[test]
+  fn wide_and_combining() {
+    let mut window = Window::new(2, 3, 0, 0);
+
+    window.addstr("e\u{301}中文");
+    assert_eq!(text(&window, 0), "e中\0");
+    assert_eq!(window.row(0)[0].grapheme(), "e\u{301}");
+    // The second wide char does not fit in the last column of the first row.
+    assert_eq!(text(&window, 1), "文\0 ");
+    assert_eq!(window.get_cur_yx(), (1, 2));
+  }
+
+  #[test]
+  fn stays_on_last_line() {
+    let mut window = Window::new(2, 4, 0, 0);
+
+    window.color_set(Style::Right);
+    window.addstr("one\ntwo\nsix");
+    assert_eq!(text(&window, 0), "one ");
+    assert_eq!(text(&window, 1), "six ");
+    assert_eq!(window.row(1)[0].style, Style::Right);
+  }
+}
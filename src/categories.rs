@@ -1,53 +1,453 @@
-const DEFAULT_TEXT: &str = include_str!("default_text");
+use crate::collection::Collection;
+use crate::difficulty::Level;
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::metadata::Metadata;
+use crate::passages::Passage;
+use crate::settings::CategorySettings;
+use crate::text::InputMode;
+
+pub struct Text {
+  pub name: String,
+  pub content: String,
+  pub metadata: Metadata,
+  pub passage: Option<Passage>,
+  pub input_mode: InputMode,
+}
+
+impl Text {
+  pub fn from_file(name: String, path: &std::path::Path) -> Result<Text> {
+    let raw = std::fs::read_to_string(path).map_err(Error::io(format!("read {}", name)))?;
+    let (front_matter, content) = Metadata::from_front_matter(&raw);
+    let metadata = Metadata::read_sidecar(path)?
+      .unwrap_or_default()
+      .merge(front_matter.unwrap_or_default());
+    Ok(Text {
+      name,
+      content: content.to_string(),
+      metadata,
+      passage: None,
+      input_mode: InputMode::Prose,
+    })
+  }
+
+  // Syntax highlighting for code, by the file name or the `language` metadata.
+  pub fn highlight(&self) -> std::vec::Vec<crate::highlight::Token> {
+    crate::highlight::detect(&self.name, self.metadata.language.as_deref())
+      .map(|language| crate::highlight::tokenize(&self.content, language))
+      .unwrap_or_default()
+  }
+}
+
+impl From<String> for Text {
+  fn from(content: String) -> Text {
+    Text {
+      name: String::new(),
+      content,
+      metadata: Metadata::default(),
+      passage: None,
+      input_mode: InputMode::Prose,
+    }
+  }
+}
+
+pub struct TextEntry {
+  pub name: String,
+  pub file_name: String,
+  pub length: usize,
+  pub difficulty: f32,
+  // History ids of what can be served from the text: each of its passages, or the whole of it.
+  pub ids: std::vec::Vec<String>,
+}
+
+// What listings and picks by difficulty need to know about a text, kept so that they do not read
+// every file each time.
+struct Details {
+  length: usize,
+  difficulty: f32,
+  // History id and difficulty of every passage, or of the whole text if it is not split.
+  passages: std::vec::Vec<(String, f32)>,
+}
 
 pub struct Categories {
   texts_dir: String,
+  history: History,
+  collections: std::cell::RefCell<std::collections::HashMap<String, std::rc::Rc<Collection>>>,
+  details: std::cell::RefCell<std::collections::HashMap<String, std::rc::Rc<Details>>>,
+  // The slow letters `details` were scored with. The scores go stale when they change.
+  scored_with: std::cell::RefCell<std::vec::Vec<char>>,
+  // Problems with individual texts or categories, which are skipped rather than failing the
+  // whole listing.
+  errors: std::cell::RefCell<std::vec::Vec<Error>>,
 }
 
 impl Categories {
   pub fn new(texts_dir: String) -> Categories {
-    Categories { texts_dir }
+    Self::with_history(texts_dir, History::default())
+  }
+
+  // Difficulty and unplayed texts go by the runs in `history`.
+  pub fn with_history(texts_dir: String, history: History) -> Categories {
+    Categories {
+      texts_dir,
+      history,
+      collections: std::cell::RefCell::new(std::collections::HashMap::new()),
+      details: std::cell::RefCell::new(std::collections::HashMap::new()),
+      scored_with: std::cell::RefCell::new(vec![]),
+      errors: std::cell::RefCell::new(vec![]),
+    }
+  }
+
+  pub fn take_errors(&self) -> std::vec::Vec<Error> {
+    self.errors.take()
+  }
+
+  fn skip(&self, error: Error) {
+    self.errors.borrow_mut().push(error);
+  }
+
+  fn read_dir(&self, category: &str) -> std::vec::Vec<std::fs::DirEntry> {
+    let path = std::path::Path::new(&self.texts_dir).join(category);
+    match std::fs::read_dir(path) {
+      Ok(entries) => entries.flatten().collect(),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => vec![],
+      Err(error) => {
+        let action = if category.is_empty() {
+          format!("read {}", self.texts_dir)
+        } else {
+          format!("read category {}", category)
+        };
+        self.skip(Error::io(action)(error));
+        vec![]
+      }
+    }
   }
 
   pub fn get_categories(&self) -> std::vec::Vec<String> {
+    self.get_subcategories("")
+  }
+
+  pub fn get_subcategories(&self, category: &str) -> std::vec::Vec<String> {
+    use std::path::Path;
     let mut categories = vec![];
-    if let Ok(entries) = std::fs::read_dir(&self.texts_dir) {
-      for entry in entries {
-        if let Ok(entry) = entry {
-          if let Ok(file_type) = entry.file_type() {
-            if file_type.is_dir() {
-              categories.push(String::from(entry.file_name().to_string_lossy()));
-            }
-          }
+    for entry in self.read_dir(category) {
+      if let Ok(file_type) = entry.file_type() {
+        let is_collection = file_type.is_file() && Collection::is_collection(&entry.path());
+        if (file_type.is_dir() || is_collection) && !Self::is_hidden(&entry) {
+          let name = Path::new(category).join(entry.file_name());
+          categories.push(name.to_string_lossy().to_string());
+        }
+      }
+    }
+    categories.sort();
+    categories
+  }
+
+  pub fn list_texts(&self, category: &str) -> std::vec::Vec<TextEntry> {
+    let mut texts = vec![];
+    self.collect_texts(category, false, &mut texts);
+    self.fill_details(&mut texts);
+    texts
+  }
+
+  // Fills in the length and difficulty of every text, dropping empty or unreadable ones.
+  fn fill_details(&self, texts: &mut std::vec::Vec<TextEntry>) {
+    let slow_letters = self.slow_letters();
+    texts.retain_mut(|entry| {
+      let details = match self.details(&entry.name, &slow_letters) {
+        Ok(details) => details,
+        Err(error) => {
+          self.skip(error);
+          return false;
         }
+      };
+      if entry.length == 0 {
+        entry.length = details.length;
       }
+      entry.difficulty = details.difficulty;
+      entry.ids = details.passages.iter().map(|(id, _)| id.clone()).collect();
+      true
+    });
+  }
+
+  // The letters to score texts with, from the history. Scores made with others are dropped.
+  fn slow_letters(&self) -> std::collections::HashSet<char> {
+    let slow_letters = crate::history::slow_letters(&self.load_history());
+    let mut sorted: std::vec::Vec<char> = slow_letters.iter().copied().collect();
+    sorted.sort();
+    if *self.scored_with.borrow() != sorted {
+      self.details.borrow_mut().clear();
+      self.scored_with.replace(sorted);
     }
-    return categories;
+    slow_letters
+  }
+
+  // Reads and scores the text the first time it is asked for.
+  fn details(
+    &self,
+    name: &str,
+    slow_letters: &std::collections::HashSet<char>,
+  ) -> Result<std::rc::Rc<Details>> {
+    if let Some(details) = self.details.borrow().get(name) {
+      return Ok(details.clone());
+    }
+    let text = self.read_text(name)?;
+    let score = |content: &str| {
+      match text.metadata.difficulty.as_deref() {
+        Some(value) => crate::difficulty::from_metadata(value),
+        None => None,
+      }
+      .unwrap_or_else(|| crate::difficulty::score(content, slow_letters))
+    };
+    let details = std::rc::Rc::new(Details {
+      length: text.content.chars().count(),
+      difficulty: score(&text.content),
+      passages: self
+        .passages(&text)
+        .into_iter()
+        .map(|passage| (crate::history::text_id(passage), score(passage)))
+        .collect(),
+    });
+    self
+      .details
+      .borrow_mut()
+      .insert(String::from(name), details.clone());
+    Ok(details)
   }
-  pub fn get_text(&self, category: &str) -> String {
+
+  pub fn list_subtree_texts(&self, category: &str) -> std::vec::Vec<TextEntry> {
+    let mut texts = vec![];
+    self.collect_texts(category, true, &mut texts);
+    texts
+  }
+
+  fn collect_texts(&self, category: &str, recursive: bool, texts: &mut std::vec::Vec<TextEntry>) {
     use std::path::Path;
-    let category_path = Path::new(&self.texts_dir).join(Path::new(category));
-    let mut file_paths = vec![];
-    if let Ok(entries) = std::fs::read_dir(category_path.clone()) {
-      for entry in entries {
-        if let Ok(entry) = entry {
-          if let Ok(file_type) = entry.file_type() {
-            if file_type.is_file() {
-              file_paths.push(entry.file_name());
-            }
-          }
+    let collection = match self.get_collection(category) {
+      Ok(collection) => collection,
+      Err(error) => {
+        self.skip(error);
+        return;
+      }
+    };
+    if let Some(collection) = collection {
+      for quote in &collection.quotes {
+        let preview: String = quote.text.chars().take(40).collect();
+        texts.push(TextEntry {
+          name: format!("{}{}{}", category, crate::collection::ID_SEPARATOR, quote.id),
+          file_name: format!("{}: {}", quote.id, preview.replace('\n', " ")),
+          length: quote.length,
+          difficulty: 0.,
+          ids: vec![],
+        });
+      }
+      return;
+    }
+
+    let mut files = vec![];
+    for entry in self.read_dir(category) {
+      if let Ok(file_type) = entry.file_type() {
+        let is_sidecar = Path::new(&entry.file_name())
+          .extension()
+          .is_some_and(|ext| ext == crate::metadata::SIDECAR_EXTENSION);
+        let is_collection = Collection::is_collection(&entry.path());
+        if file_type.is_file() && !is_sidecar && !is_collection && !Self::is_hidden(&entry) {
+          let name = Path::new(category).join(entry.file_name());
+          files.push(TextEntry {
+            name: name.to_string_lossy().to_string(),
+            file_name: entry.file_name().to_string_lossy().to_string(),
+            length: 0,
+            difficulty: 0.,
+            ids: vec![],
+          });
         }
       }
     }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    texts.extend(files);
+
+    if recursive {
+      for subcategory in self.get_subcategories(category) {
+        self.collect_texts(&subcategory, true, texts);
+      }
+    }
+  }
+
+  // `None` if the category is a directory rather than a collection file.
+  fn get_collection(&self, category: &str) -> Result<Option<std::rc::Rc<Collection>>> {
+    if let Some(collection) = self.collections.borrow().get(category) {
+      return Ok(Some(collection.clone()));
+    }
+    let path = std::path::Path::new(&self.texts_dir).join(category);
+    if !Collection::is_collection(&path) {
+      return Ok(None);
+    }
+    let collection = std::rc::Rc::new(Collection::load(&path)?);
+    self
+      .collections
+      .borrow_mut()
+      .insert(String::from(category), collection.clone());
+    Ok(Some(collection))
+  }
+
+  fn load_history(&self) -> std::vec::Vec<crate::history::Record> {
+    self.history.load().unwrap_or_else(|error| {
+      self.skip(error);
+      vec![]
+    })
+  }
+
+  fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+  }
+
+  pub fn get_text_by_name(&self, name: &str) -> Result<Text> {
+    self.read_text(name).map(|text| self.choose_passage(text))
+  }
+
+  fn read_text(&self, name: &str) -> Result<Text> {
+    let collection_quote = match name.rsplit_once(crate::collection::ID_SEPARATOR) {
+      Some((category, id)) => self.get_collection(category)?.map(|collection| (collection, id)),
+      None => None,
+    };
+    let text = match collection_quote {
+      Some((collection, id)) => {
+        let quote = collection
+          .get(id)
+          .ok_or_else(|| Error::TextNotFound(String::from(name)))?;
+        Text {
+          metadata: quote.metadata.clone(),
+          ..Text::from(quote.text.clone())
+        }
+      }
+      None => {
+        let path = std::path::Path::new(&self.texts_dir).join(name);
+        Text::from_file(String::from(name), &path)?
+      }
+    };
+    let text = Text {
+      name: String::from(name),
+      ..text
+    };
+    let settings = self.get_text_settings(name);
+    let content = settings.normalization.apply(&text.content);
+    if content.trim().is_empty() {
+      return Err(Error::EmptyText(String::from(name)));
+    }
+    Ok(Text {
+      content,
+      input_mode: settings.input_mode,
+      ..text
+    })
+  }
+
+  // Like `get_text_by_name`, but records the error and moves on.
+  fn try_text_by_name(&self, name: &str) -> Option<Text> {
+    self.get_text_by_name(name).map_err(|error| self.skip(error)).ok()
+  }
+
+  fn get_text_settings(&self, name: &str) -> CategorySettings {
+    let category = std::path::Path::new(name)
+      .parent()
+      .map(|category| category.to_string_lossy().to_string())
+      .unwrap_or_default();
+    CategorySettings::load(&self.texts_dir, &category)
+  }
+
+  // The passages the text is split into, or the whole text if it is not split.
+  fn passages<'a>(&self, text: &'a Text) -> std::vec::Vec<&'a str> {
+    let settings = self.get_text_settings(&text.name);
+    let passages = crate::passages::split(&text.content, settings.passages);
+    if passages.len() < 2 {
+      vec![&text.content]
+    } else {
+      passages
+    }
+  }
+
+  fn choose_passage(&self, text: Text) -> Text {
+    use rand::Rng;
+    let index = rand::thread_rng().gen_range(0..self.passages(&text).len());
+    self.passage_at(text, index)
+  }
+
+  // The passage at `index` in `passages`, or the text itself if it is not split.
+  fn passage_at(&self, text: Text, index: usize) -> Text {
+    let passages = self.passages(&text);
+    if passages.len() < 2 {
+      return text;
+    }
+    Text {
+      content: passages[index].to_string(),
+      passage: Some(Passage {
+        index,
+        count: passages.len(),
+      }),
+      ..text
+    }
+  }
+
+  pub fn get_text(&self, category: &str) -> Text {
+    use rand::seq::SliceRandom;
+    let mut texts = self.list_subtree_texts(category);
+    texts.shuffle(&mut rand::thread_rng());
+    if let Some(text) = texts.iter().find_map(|entry| self.try_text_by_name(&entry.name)) {
+      return text;
+    }
+
+    let starter = crate::starter::texts(crate::starter::language_for_category(category));
+    let starter = starter
+      .choose(&mut rand::thread_rng())
+      .unwrap_or(&&crate::starter::LIBRARY[0]);
+    starter.to_text()
+  }
 
+  // Picks a passage of the level, rather than a text that is of the level as a whole.
+  pub fn get_text_with_difficulty(&self, category: &str, level: Level) -> Result<Text> {
     use rand::seq::SliceRandom;
-    if let Some(file_path) = file_paths.choose(&mut rand::thread_rng()) {
-      if let Ok(text) = std::fs::read_to_string(category_path.join(Path::new(file_path))) {
-        return text;
+    let slow_letters = self.slow_letters();
+    let mut matching = vec![];
+    for entry in self.list_subtree_texts(category) {
+      match self.details(&entry.name, &slow_letters) {
+        Ok(details) => {
+          for (index, (_, difficulty)) in details.passages.iter().enumerate() {
+            if Level::from_score(*difficulty) == level {
+              matching.push((entry.name.clone(), index));
+            }
+          }
+        }
+        Err(error) => self.skip(error),
       }
     }
+    let (name, index) = matching
+      .choose(&mut rand::thread_rng())
+      .ok_or_else(|| Error::NoTextsOfLevel {
+        category: String::from(category),
+        level: level.name(),
+      })?;
+    Ok(self.passage_at(self.read_text(name)?, *index))
+  }
 
-    return String::from(DEFAULT_TEXT);
+  // Picks the first text without a run in the history, falling back to the least recently played.
+  pub fn get_next_unplayed(&self, category: &str) -> Option<Text> {
+    let mut texts = self.list_subtree_texts(category);
+    self.fill_details(&mut texts);
+    let history = self.load_history();
+    let last_played = |entry: &TextEntry| {
+      history
+        .iter()
+        .filter(|record| record.get("id").is_some_and(|id| entry.ids.iter().any(|x| x == id)))
+        .map(|record| record.time)
+        .max()
+    };
+    let mut texts: std::vec::Vec<(u64, &TextEntry)> = texts
+      .iter()
+      .map(|entry| (last_played(entry).map_or(0, |time| time + 1), entry))
+      .collect();
+    texts.sort_by_key(|(last_played, _)| *last_played);
+    texts
+      .into_iter()
+      .find_map(|(_, entry)| self.try_text_by_name(&entry.name))
   }
 }
 
@@ -86,7 +486,215 @@ mod tests {
     file.write_all("TestContent".as_bytes()).unwrap();
     let c = Categories::new(dir.clone());
 
-    assert_eq!(c.get_text("cat2"), "TestContent");
+    assert_eq!(c.get_text("cat2").content, "TestContent");
+    assert_eq!(c.get_text_by_name("cat2/test").unwrap().content, "TestContent");
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn get_text_metadata() {
+    let dir = String::from("test_8c1d0b7e2"); // random
+    std::fs::create_dir(dir.clone()).unwrap();
+    std::fs::create_dir(dir.clone() + "/cat1").unwrap();
+    std::fs::write(
+      dir.clone() + "/cat1/test",
+      "---\ntitle: Test title\n---\nTestContent",
+    )
+    .unwrap();
+    std::fs::write(dir.clone() + "/cat1/test.meta", "author: Test author\n").unwrap();
+
+    let c = Categories::new(dir.clone());
+    let text = c.get_text("cat1");
+
+    assert_eq!(text.name, "cat1/test");
+    assert_eq!(text.content, "TestContent");
+    assert_eq!(text.metadata.title.as_deref(), Some("Test title"));
+    assert_eq!(text.metadata.author.as_deref(), Some("Test author"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn list_texts() {
+    let dir = String::from("test_f02c9d4a1"); // random
+    std::fs::create_dir(dir.clone()).unwrap();
+    std::fs::create_dir(dir.clone() + "/cat1").unwrap();
+    std::fs::write(dir.clone() + "/cat1/b", "Second").unwrap();
+    std::fs::write(dir.clone() + "/cat1/a", "---\ntitle: First\n---\nFirst").unwrap();
+    std::fs::write(dir.clone() + "/cat1/a.meta", "author: Someone\n").unwrap();
+
+    let c = Categories::new(dir.clone());
+    let texts = c.list_texts("cat1");
+
+    let names: std::vec::Vec<_> = texts.iter().map(|text| &text.file_name[..]).collect();
+    assert_eq!(names, vec!["a", "b"]);
+    assert_eq!(texts[0].name, "cat1/a");
+    assert_eq!(texts[0].length, 5);
+    assert_eq!(texts[1].length, 6);
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn nested_categories() {
+    let dir = String::from("test_5be17a3c0"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat1/sub1/deep").unwrap();
+    std::fs::create_dir_all(dir.clone() + "/cat1/sub2").unwrap();
+    std::fs::create_dir_all(dir.clone() + "/cat1/.hidden").unwrap();
+    std::fs::write(dir.clone() + "/cat1/top", "Top").unwrap();
+    std::fs::write(dir.clone() + "/cat1/sub1/deep/text", "Deep").unwrap();
+
+    let c = Categories::new(dir.clone());
+
+    assert_eq!(c.get_categories(), vec!["cat1"]);
+    assert_eq!(c.get_subcategories("cat1"), vec!["cat1/sub1", "cat1/sub2"]);
+    assert_eq!(c.get_subcategories("cat1/sub1"), vec!["cat1/sub1/deep"]);
+
+    let names: std::vec::Vec<_> = c.list_texts("cat1").into_iter().map(|text| text.name).collect();
+    assert_eq!(names, vec!["cat1/top"]);
+    let names: std::vec::Vec<_> = c
+      .list_subtree_texts("cat1")
+      .into_iter()
+      .map(|text| text.name)
+      .collect();
+    assert_eq!(names, vec!["cat1/top", "cat1/sub1/deep/text"]);
+
+    assert_eq!(c.get_text("cat1/sub1").content, "Deep");
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn get_text_passage() {
+    let dir = String::from("test_71a9e3c5d"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat1").unwrap();
+    std::fs::write(dir.clone() + "/cat1/.category", "passages: paragraph\n").unwrap();
+    std::fs::write(dir.clone() + "/cat1/test", "First\n\nSecond\n").unwrap();
+
+    let c = Categories::new(dir.clone());
+    let text = c.get_text("cat1");
+    let passage = text.passage.unwrap();
+
+    assert_eq!(passage.count, 2);
+    assert_eq!(text.content, ["First", "Second"][passage.index]);
+    assert_eq!(c.list_texts("cat1")[0].length, "First\n\nSecond\n".len());
+    // History knows the passage by its own content.
+    let ids = &c.list_texts("cat1")[0].ids;
+    assert_eq!(ids.len(), 2);
+    assert_eq!(ids[passage.index], crate::history::text_id(&text.content));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn get_text_normalized() {
+    let dir = String::from("test_3e8f61b2a"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat1").unwrap();
+    std::fs::write(dir.clone() + "/cat1/.category", "collapse_whitespace: yes\n").unwrap();
+    std::fs::write(dir.clone() + "/cat1/test", "\u{201c}Quoted\u{201d}   text\r\n").unwrap();
+
+    let c = Categories::new(dir.clone());
+
+    assert_eq!(c.get_text("cat1").content, "\"Quoted\" text\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn collections() {
+    let dir = String::from("test_c4b2f8e06"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat1").unwrap();
+    std::fs::write(dir.clone() + "/cat1/text", "Plain text").unwrap();
+    std::fs::write(
+      dir.clone() + "/cat1/quotes.json",
+      r#"[{"id": "q1", "text": "First quote"}, {"id": "q2", "text": "Second", "author": "A"}]"#,
+    )
+    .unwrap();
+
+    let c = Categories::new(dir.clone());
+
+    assert_eq!(c.get_subcategories("cat1"), vec!["cat1/quotes.json"]);
+    let names: std::vec::Vec<_> = c.list_texts("cat1").into_iter().map(|text| text.name).collect();
+    assert_eq!(names, vec!["cat1/text"]);
+
+    let quotes = c.list_texts("cat1/quotes.json");
+    assert_eq!(quotes.len(), 2);
+    assert_eq!(quotes[1].name, "cat1/quotes.json#q2");
+    assert_eq!(quotes[1].length, 6);
+    assert_eq!(c.list_subtree_texts("cat1").len(), 3);
+
+    let text = c.get_text_by_name("cat1/quotes.json#q2").unwrap();
+    assert_eq!(text.content, "Second");
+    assert_eq!(text.name, "cat1/quotes.json#q2");
+    assert_eq!(text.metadata.author.as_deref(), Some("A"));
+    assert!(matches!(
+      c.get_text_by_name("cat1/quotes.json#missing"),
+      Err(Error::TextNotFound(_))
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn difficulty() {
+    let dir = String::from("test_5d7c2a91f"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat").unwrap();
+    std::fs::write(dir.clone() + "/cat/easy", "the cat sat on the mat").unwrap();
+    std::fs::write(dir.clone() + "/cat/hard", "fn f(x: &[u8]) -> u8 { x[0] ^ 0x2A }").unwrap();
+    std::fs::write(dir.clone() + "/cat/tagged", "---\ndifficulty: hard\n---\nthe end").unwrap();
+    let c = Categories::new(dir.clone());
+
+    let texts = c.list_texts("cat");
+    let levels: std::vec::Vec<_> = texts.iter().map(|text| Level::from_score(text.difficulty)).collect();
+    assert_eq!(levels, vec![Level::Easy, Level::Hard, Level::Hard]);
+    assert_eq!(c.get_text_with_difficulty("cat", Level::Easy).unwrap().name, "cat/easy");
+    assert!(matches!(
+      c.get_text_with_difficulty("cat", Level::Medium),
+      Err(Error::NoTextsOfLevel { .. })
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn difficulty_by_passage() {
+    let dir = String::from("test_b84e1f07c"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat").unwrap();
+    std::fs::write(dir.clone() + "/cat/.category", "passages: paragraph\n").unwrap();
+    let content = "the cat sat on the mat\n\nfn f(x: &[u8]) -> u8 { x[0] ^ 0x2A }\n";
+    std::fs::write(dir.clone() + "/cat/mixed", content).unwrap();
+    let c = Categories::new(dir.clone());
+
+    for _ in 0..5 {
+      let text = c.get_text_with_difficulty("cat", Level::Hard).unwrap();
+      assert_eq!(text.passage.unwrap().index, 1);
+      let text = c.get_text_with_difficulty("cat", Level::Easy).unwrap();
+      assert_eq!(text.content, "the cat sat on the mat");
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn skip_empty_texts() {
+    let dir = String::from("test_e0b3c5d18"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat/Bulgarian").unwrap();
+    std::fs::write(dir.clone() + "/cat/empty", "").unwrap();
+    std::fs::write(dir.clone() + "/cat/blank", " \n\u{200b}\n").unwrap();
+    std::fs::write(dir.clone() + "/cat/text", "Content").unwrap();
+    std::fs::write(dir.clone() + "/cat/Bulgarian/empty", "").unwrap();
+    let c = Categories::new(dir.clone());
+
+    assert_eq!(c.list_texts("cat").len(), 1);
+    assert_eq!(c.take_errors().len(), 2);
+    assert!(matches!(c.get_text_by_name("cat/empty"), Err(Error::EmptyText(_))));
+    for _ in 0..10 {
+      assert_eq!(c.get_text("cat").content, "Content");
+    }
+    let starter = c.get_text("cat/Bulgarian");
+    assert_eq!(starter.metadata.language.as_deref(), Some("bg"));
+    assert!(!c.get_text("missing").content.is_empty());
 
     std::fs::remove_dir_all(dir).unwrap();
   }
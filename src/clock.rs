@@ -0,0 +1,73 @@
+// A source of timestamps for typing sessions, measured from an arbitrary starting point.
+pub trait Clock {
+  fn now(&self) -> std::time::Duration;
+}
+
+pub struct SystemClock {
+  start: std::time::Instant,
+}
+
+impl SystemClock {
+  pub fn new() -> SystemClock {
+    SystemClock {
+      start: std::time::Instant::now(),
+    }
+  }
+}
+
+impl Default for SystemClock {
+  fn default() -> SystemClock {
+    SystemClock::new()
+  }
+}
+
+impl Clock for SystemClock {
+  fn now(&self) -> std::time::Duration {
+    self.start.elapsed()
+  }
+}
+
+// Only moves when told to. Clones share the same time, so a test can keep one to drive the clock
+// it handed to a `TextManager`, and a replay can set it to recorded timestamps.
+#[derive(Clone, Default)]
+pub struct MockClock {
+  time: std::rc::Rc<std::cell::Cell<std::time::Duration>>,
+}
+
+impl MockClock {
+  pub fn new() -> MockClock {
+    MockClock::default()
+  }
+
+  pub fn set(&self, time: std::time::Duration) {
+    self.time.set(time);
+  }
+
+  pub fn advance(&self, duration: std::time::Duration) {
+    self.time.set(self.time.get() + duration);
+  }
+}
+
+impl Clock for MockClock {
+  fn now(&self) -> std::time::Duration {
+    self.time.get()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mock_clones_share_time() {
+    let clock = MockClock::new();
+    let handle = clock.clone();
+
+    handle.advance(std::time::Duration::from_millis(250));
+    handle.advance(std::time::Duration::from_millis(250));
+    assert_eq!(clock.now(), std::time::Duration::from_millis(500));
+
+    handle.set(std::time::Duration::from_secs(3));
+    assert_eq!(clock.now(), std::time::Duration::from_secs(3));
+  }
+}
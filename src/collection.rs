@@ -0,0 +1,167 @@
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+
+pub const COLLECTION_EXTENSION: &str = "json";
+pub const ID_SEPARATOR: char = '#';
+
+#[derive(serde::Deserialize)]
+struct RawQuote {
+  id: Option<serde_json::Value>,
+  text: String,
+  title: Option<String>,
+  author: Option<String>,
+  source: Option<String>,
+  language: Option<String>,
+  difficulty: Option<String>,
+  #[serde(default)]
+  tags: std::vec::Vec<String>,
+  length: Option<usize>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum RawCollection {
+  Quotes(std::vec::Vec<RawQuote>),
+  WithMetadata {
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
+    quotes: std::vec::Vec<RawQuote>,
+  },
+}
+
+pub struct Quote {
+  pub id: String,
+  pub text: String,
+  pub metadata: Metadata,
+  pub length: usize,
+}
+
+// A single file holding many short texts, each addressed by its `id`.
+pub struct Collection {
+  pub quotes: std::vec::Vec<Quote>,
+  index: std::collections::HashMap<String, usize>,
+}
+
+impl Collection {
+  pub fn is_collection(path: &std::path::Path) -> bool {
+    path.extension().is_some_and(|ext| ext == COLLECTION_EXTENSION) && path.is_file()
+  }
+
+  pub fn load(path: &std::path::Path) -> Result<Collection> {
+    let content =
+      std::fs::read_to_string(path).map_err(Error::io(format!("read {}", path.display())))?;
+    Self::parse(&content).map_err(|error| Error::InvalidCollection {
+      path: path.display().to_string(),
+      message: error.to_string(),
+    })
+  }
+
+  pub fn parse(content: &str) -> serde_json::Result<Collection> {
+    let (defaults, raw_quotes) = match serde_json::from_str(content)? {
+      RawCollection::Quotes(quotes) => (Metadata::default(), quotes),
+      RawCollection::WithMetadata {
+        author,
+        source,
+        language,
+        quotes,
+      } => (
+        Metadata {
+          author,
+          source,
+          language,
+          ..Metadata::default()
+        },
+        quotes,
+      ),
+    };
+
+    let mut quotes = vec![];
+    let mut index = std::collections::HashMap::new();
+    for (i, raw) in raw_quotes.into_iter().enumerate() {
+      // Quotes without an id fall back to their position, which changes if the file is reordered.
+      let id = match raw.id {
+        Some(serde_json::Value::String(id)) => id,
+        Some(serde_json::Value::Number(id)) => id.to_string(),
+        _ => (i + 1).to_string(),
+      };
+      if raw.text.trim().is_empty() || index.contains_key(&id) {
+        continue;
+      }
+      let length = match raw.length {
+        Some(length) => length,
+        None => raw.text.chars().count(),
+      };
+      let metadata = Metadata {
+        title: raw.title,
+        author: raw.author,
+        source: raw.source,
+        language: raw.language,
+        difficulty: raw.difficulty,
+        tags: raw.tags,
+      };
+      index.insert(id.clone(), quotes.len());
+      quotes.push(Quote {
+        id,
+        length,
+        text: raw.text,
+        metadata: defaults.clone().merge(metadata),
+      });
+    }
+    Ok(Collection { quotes, index })
+  }
+
+  pub fn get(&self, id: &str) -> Option<&Quote> {
+    self.index.get(id).map(|i| &self.quotes[*i])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn array() {
+    let collection = Collection::parse(
+      r#"[
+        {"id": "a", "text": "First quote", "author": "Someone"},
+        {"id": 7, "text": "Second quote", "length": 3},
+        {"text": "Third quote"},
+        {"id": "a", "text": "Duplicate id"}
+      ]"#,
+    )
+    .unwrap();
+
+    let ids: std::vec::Vec<_> = collection.quotes.iter().map(|quote| &quote.id[..]).collect();
+    assert_eq!(ids, vec!["a", "7", "3"]);
+    assert_eq!(collection.get("a").unwrap().text, "First quote");
+    assert_eq!(collection.get("a").unwrap().metadata.author.as_deref(), Some("Someone"));
+    assert_eq!(collection.get("7").unwrap().length, 3);
+    assert_eq!(collection.get("3").unwrap().length, 11);
+    assert!(collection.get("missing").is_none());
+  }
+
+  #[test]
+  fn with_metadata() {
+    let collection = Collection::parse(
+      r#"{"author": "Default", "language": "en", "quotes": [
+        {"id": "x", "text": "Quote", "author": "Override"},
+        {"id": "y", "text": "Other"}
+      ]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(collection.get("x").unwrap().metadata.author.as_deref(), Some("Override"));
+    assert_eq!(collection.get("y").unwrap().metadata.author.as_deref(), Some("Default"));
+    assert_eq!(collection.get("y").unwrap().metadata.language.as_deref(), Some("en"));
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(Collection::parse("not json").is_err());
+    assert!(Collection::parse(r#"{"no": "quotes"}"#).is_err());
+  }
+}
@@ -0,0 +1,87 @@
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+// Spacing accents that dead keys send on some layouts and terminals, with the combining marks
+// they stand for.
+const DEAD_KEYS: [(char, char); 13] = [
+  ('`', '\u{300}'),
+  ('´', '\u{301}'),
+  ('^', '\u{302}'),
+  ('~', '\u{303}'),
+  ('¯', '\u{304}'),
+  ('˘', '\u{306}'),
+  ('˙', '\u{307}'),
+  ('¨', '\u{308}'),
+  ('˚', '\u{30a}'),
+  ('˝', '\u{30b}'),
+  ('ˇ', '\u{30c}'),
+  ('¸', '\u{327}'),
+  ('˛', '\u{328}'),
+];
+
+// The combining mark `c` adds to the letter after it when it comes from a dead key.
+pub fn dead_key_mark(c: char) -> Option<char> {
+  if canonical_combining_class(c) != 0 {
+    return Some(c);
+  }
+  DEAD_KEYS
+    .iter()
+    .find(|(accent, _)| *accent == c)
+    .map(|(_, mark)| *mark)
+}
+
+// Whether `grapheme` has `mark` on it, precomposed or not.
+pub fn has_mark(grapheme: &str, mark: char) -> bool {
+  grapheme.nfd().any(|c| c == mark)
+}
+
+// The letter `base` with `mark` on it, precomposed where Unicode has a char for it.
+pub fn compose(base: char, mark: char) -> String {
+  format!("{}{}", base, mark).nfc().collect()
+}
+
+// Whether `grapheme` is `partial` with more combining chars after it.
+pub fn is_start_of(partial: &str, grapheme: &str) -> bool {
+  let mut full = grapheme.nfd();
+  let mut count = 0;
+  for c in partial.nfd() {
+    if full.next() != Some(c) {
+      return false;
+    }
+    count += 1;
+  }
+  count > 0 && full.next().is_some()
+}
+
+// Whether the two are the same text, whichever way their accents are encoded.
+pub fn equivalent(a: &str, b: &str) -> bool {
+  a == b || a.nfc().eq(b.nfc())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dead_keys() {
+    assert_eq!(dead_key_mark('´'), Some('\u{301}'));
+    assert_eq!(dead_key_mark('\u{308}'), Some('\u{308}'));
+    assert_eq!(dead_key_mark('e'), None);
+
+    assert!(has_mark("é", '\u{301}'));
+    assert!(!has_mark("e", '\u{301}'));
+    assert_eq!(compose('e', '\u{301}'), "é");
+    assert_eq!(compose('q', '\u{301}'), "q\u{301}");
+
+    assert!(is_start_of("e", "é"));
+    assert!(!is_start_of("é", "é"));
+    assert!(!is_start_of("a", "é"));
+  }
+
+  #[test]
+  fn nfc_and_nfd() {
+    assert!(equivalent("é", "e\u{301}"));
+    assert!(equivalent("ỗ", "o\u{302}\u{303}"));
+    assert!(!equivalent("é", "e"));
+  }
+}
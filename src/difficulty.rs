@@ -0,0 +1,166 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+  Easy,
+  Medium,
+  Hard,
+}
+
+impl Level {
+  pub fn from_score(score: f32) -> Level {
+    if score < 25. {
+      Level::Easy
+    } else if score < 45. {
+      Level::Medium
+    } else {
+      Level::Hard
+    }
+  }
+
+  pub fn parse(value: &str) -> Option<Level> {
+    match &value.trim().to_lowercase()[..] {
+      "easy" => Some(Level::Easy),
+      "medium" => Some(Level::Medium),
+      "hard" => Some(Level::Hard),
+      _ => None,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Level::Easy => "easy",
+      Level::Medium => "medium",
+      Level::Hard => "hard",
+    }
+  }
+}
+
+// A `difficulty` metadata value, either a score or a level name.
+pub fn from_metadata(value: &str) -> Option<f32> {
+  if let Ok(score) = value.trim().parse::<f32>() {
+    return Some(score.clamp(0., 100.));
+  }
+  Level::parse(value).map(|level| match level {
+    Level::Easy => 15.,
+    Level::Medium => 40.,
+    Level::Hard => 75.,
+  })
+}
+
+const RARE_LETTERS: &str = "qjzxkvwyщюжцшфьѝъ";
+const PUNCTUATION: &str = ".,;:!?'\"-()";
+
+// Each feature is a ratio scaled so that 1.0 is "very hard", paired with its weight in the score.
+const FEATURES: [(f32, f32); 7] = [
+  (0.08, 0.15), // rare letters per letter
+  (0.08, 0.15), // punctuation per character
+  (0.1, 0.1),   // capitals per letter
+  (0.05, 0.1),  // digits per character
+  (0.05, 0.15), // symbols per character
+  (5., 0.15),   // average word length above 3
+  (0.3, 0.2),   // historically slow letters per letter
+];
+
+// Scores a text from 0 (trivial) to 100 (very hard).
+pub fn score(text: &str, slow_letters: &std::collections::HashSet<char>) -> f32 {
+  let mut letters = 0;
+  let mut chars = 0;
+  let mut counts = [0; 5];
+  let mut slow = 0;
+  let mut words = 0;
+  let mut word_letters = 0;
+  let mut in_word = false;
+  for c in text.chars() {
+    if c.is_whitespace() {
+      in_word = false;
+      continue;
+    }
+    chars += 1;
+    if c.is_alphabetic() {
+      letters += 1;
+      let lower = c.to_lowercase().next().unwrap_or(c);
+      if RARE_LETTERS.contains(lower) {
+        counts[0] += 1;
+      }
+      if c.is_uppercase() {
+        counts[2] += 1;
+      }
+      if slow_letters.contains(&lower) {
+        slow += 1;
+      }
+      if !in_word {
+        words += 1;
+        in_word = true;
+      }
+      word_letters += 1;
+    } else if c.is_numeric() {
+      counts[3] += 1;
+    } else if PUNCTUATION.contains(c) {
+      counts[1] += 1;
+    } else {
+      counts[4] += 1;
+    }
+  }
+
+  let ratio = |count: usize, total: usize| {
+    if total == 0 {
+      0.
+    } else {
+      count as f32 / total as f32
+    }
+  };
+  let average_word_length = ratio(word_letters, words);
+  let values = [
+    ratio(counts[0], letters),
+    ratio(counts[1], chars),
+    ratio(counts[2], letters),
+    ratio(counts[3], chars),
+    ratio(counts[4], chars),
+    (average_word_length - 3.).max(0.),
+    ratio(slow, letters),
+  ];
+
+  let mut score = 0.;
+  for (value, (hard, weight)) in values.iter().zip(FEATURES.iter()) {
+    score += (value / hard).min(1.) * weight;
+  }
+  score * 100.
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ordering() {
+    let no_slow = std::collections::HashSet::new();
+    let easy = score("the cat sat on the mat and the dog ate", &no_slow);
+    let medium = score("The quick brown fox jumps over the lazy dog.", &no_slow);
+    let hard = score("fn main() { let x: Vec<u8> = vec![0x1F; 42]; }", &no_slow);
+
+    assert!(easy < medium);
+    assert!(medium < hard);
+    assert_eq!(Level::from_score(easy), Level::Easy);
+    assert_eq!(Level::from_score(hard), Level::Hard);
+  }
+
+  #[test]
+  fn slow_letters() {
+    let text = "the cat sat on the mat";
+    let slow: std::collections::HashSet<char> = "ta".chars().collect();
+
+    assert!(score(text, &slow) > score(text, &std::collections::HashSet::new()));
+  }
+
+  #[test]
+  fn metadata_override() {
+    assert_eq!(from_metadata("hard").map(Level::from_score), Some(Level::Hard));
+    assert_eq!(from_metadata(" Easy ").map(Level::from_score), Some(Level::Easy));
+    assert_eq!(from_metadata("42"), Some(42.));
+    assert_eq!(from_metadata("tricky"), None);
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(score("", &std::collections::HashSet::new()), 0.);
+  }
+}
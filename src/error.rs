@@ -0,0 +1,90 @@
+#[derive(Debug)]
+pub enum Error {
+  // `action` completes "could not ...", e.g. "write history".
+  Io {
+    action: String,
+    source: std::io::Error,
+  },
+  EmptyText(String),
+  TextNotFound(String),
+  NoTextsOfLevel {
+    category: String,
+    level: &'static str,
+  },
+  InvalidCollection {
+    path: String,
+    message: String,
+  },
+  InvalidHistory {
+    line: usize,
+  },
+  InvalidBook {
+    path: String,
+    message: String,
+  },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+  // For use with `map_err`, e.g. `.map_err(Error::io("read texts"))`.
+  pub fn io(action: impl Into<String>) -> impl FnOnce(std::io::Error) -> Error {
+    let action = action.into();
+    move |source| Error::Io { action, source }
+  }
+}
+
+impl std::fmt::Display for Error {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Error::Io { action, source } => match source.kind() {
+        // The kind alone reads better ("permission denied") unless it says nothing useful.
+        std::io::ErrorKind::Other => write!(f, "could not {}: {}", action, source),
+        kind => write!(f, "could not {}: {}", action, kind),
+      },
+      Error::EmptyText(name) if name.is_empty() => write!(f, "the text is empty"),
+      Error::EmptyText(name) => write!(f, "{} is empty", name),
+      Error::TextNotFound(name) => write!(f, "text {} not found", name),
+      Error::NoTextsOfLevel { category, level } => write!(f, "no {} texts in {}", level, category),
+      Error::InvalidCollection { path, message } => {
+        write!(f, "invalid collection {}: {}", path, message)
+      }
+      Error::InvalidHistory { line } => write!(f, "history line {} is malformed", line),
+      Error::InvalidBook { path, message } => write!(f, "invalid book {}: {}", path, message),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Io { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn messages() {
+    let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+
+    assert_eq!(
+      Error::io("write history")(denied).to_string(),
+      "could not write history: permission denied"
+    );
+    assert_eq!(Error::EmptyText(String::from("Basic/x")).to_string(), "Basic/x is empty");
+    assert_eq!(Error::InvalidHistory { line: 3 }.to_string(), "history line 3 is malformed");
+    assert_eq!(
+      Error::InvalidBook {
+        path: String::from("a.epub"),
+        message: String::from("no readable text found"),
+      }
+      .to_string(),
+      "invalid book a.epub: no readable text found"
+    );
+  }
+}
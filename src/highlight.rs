@@ -0,0 +1,315 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+  Plain,
+  Keyword,
+  String,
+  Comment,
+  Number,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token {
+  pub kind: TokenKind,
+  pub start: usize,
+  pub end: usize,
+}
+
+pub struct Language {
+  pub name: &'static str,
+  pub extensions: &'static [&'static str],
+  pub keywords: &'static [&'static str],
+  pub line_comments: &'static [&'static str],
+  pub block_comment: Option<(&'static str, &'static str)>,
+  // Longest delimiters first, so that `"""` is not read as an empty `""` string.
+  pub string_delimiters: &'static [&'static str],
+  // `'a'` is a character literal, but `'a` is a lifetime.
+  pub char_literals: bool,
+}
+
+pub const RUST: Language = Language {
+  name: "rust",
+  extensions: &["rs"],
+  keywords: &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+  ],
+  line_comments: &["//"],
+  block_comment: Some(("/*", "*/")),
+  string_delimiters: &["\""],
+  char_literals: true,
+};
+
+pub const PYTHON: Language = Language {
+  name: "python",
+  extensions: &["py"],
+  keywords: &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+  ],
+  line_comments: &["#"],
+  block_comment: None,
+  string_delimiters: &["\"\"\"", "'''", "\"", "'"],
+  char_literals: false,
+};
+
+pub const JAVASCRIPT: Language = Language {
+  name: "javascript",
+  extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+  keywords: &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete",
+    "do", "else", "export", "extends", "false", "finally", "for", "function", "if", "import", "in",
+    "instanceof", "let", "new", "null", "of", "return", "static", "super", "switch", "this",
+    "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "yield",
+  ],
+  line_comments: &["//"],
+  block_comment: Some(("/*", "*/")),
+  string_delimiters: &["\"", "'", "`"],
+  char_literals: false,
+};
+
+const LANGUAGES: [&Language; 3] = [&RUST, &PYTHON, &JAVASCRIPT];
+
+// Picks a language from the `language` metadata, the file extension or a category named after it.
+pub fn detect(text_name: &str, language: Option<&str>) -> Option<&'static Language> {
+  let by_name = |name: &str| {
+    let name = name.to_lowercase();
+    let name = match &name[..] {
+      "rs" => "rust",
+      "py" => "python",
+      "js" => "javascript",
+      name => name,
+    };
+    LANGUAGES
+      .iter()
+      .find(|language| language.name == name)
+      .copied()
+  };
+
+  if let Some(language) = language.and_then(by_name) {
+    return Some(language);
+  }
+
+  let path = std::path::Path::new(text_name);
+  if let Some(extension) = path.extension() {
+    let extension = extension.to_string_lossy().to_lowercase();
+    let language = LANGUAGES
+      .iter()
+      .find(|language| language.extensions.contains(&&extension[..]));
+    if let Some(language) = language {
+      return Some(language);
+    }
+  }
+
+  path
+    .parent()?
+    .components()
+    .rev()
+    .find_map(|component| by_name(&component.as_os_str().to_string_lossy()))
+}
+
+pub fn tokenize(text: &str, language: &Language) -> std::vec::Vec<Token> {
+  let mut tokens: std::vec::Vec<Token> = vec![];
+  let mut push = |kind: TokenKind, start: usize, end: usize| {
+    match tokens.last_mut() {
+      Some(last) if last.kind == kind && last.end == start => last.end = end,
+      _ => tokens.push(Token { kind, start, end }),
+    }
+  };
+
+  let mut i = 0;
+  while i < text.len() {
+    let rest = &text[i..];
+    let c = rest.chars().next().unwrap_or_default();
+
+    if language.line_comments.iter().any(|comment| rest.starts_with(comment)) {
+      let end = rest.find('\n').map_or(text.len(), |end| i + end);
+      push(TokenKind::Comment, i, end);
+      i = end;
+      continue;
+    }
+
+    if let Some((open, close)) = language.block_comment {
+      if let Some(body) = rest.strip_prefix(open) {
+        let end = body
+          .find(close)
+          .map_or(text.len(), |end| i + open.len() + end + close.len());
+        push(TokenKind::Comment, i, end);
+        i = end;
+        continue;
+      }
+    }
+
+    if language.char_literals && c == '\'' {
+      if let Some(len) = char_literal_len(rest) {
+        push(TokenKind::String, i, i + len);
+        i += len;
+        continue;
+      }
+    }
+
+    if let Some(delimiter) = language
+      .string_delimiters
+      .iter()
+      .find(|delimiter| rest.starts_with(*delimiter))
+    {
+      let end = i + delimiter.len() + string_len(&rest[delimiter.len()..], delimiter);
+      push(TokenKind::String, i, end);
+      i = end;
+      continue;
+    }
+
+    if c.is_alphanumeric() || c == '_' {
+      let len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+      let word = &rest[..len];
+      let kind = if c.is_ascii_digit() {
+        TokenKind::Number
+      } else if language.keywords.contains(&word) {
+        TokenKind::Keyword
+      } else {
+        TokenKind::Plain
+      };
+      push(kind, i, i + len);
+      i += len;
+      continue;
+    }
+
+    push(TokenKind::Plain, i, i + c.len_utf8());
+    i += c.len_utf8();
+  }
+  tokens
+}
+
+impl TokenKind {
+  // The kind of the token with the byte at `i`.
+  pub fn at(tokens: &[Token], i: usize) -> TokenKind {
+    match tokens.binary_search_by(|token| {
+      if token.end <= i {
+        std::cmp::Ordering::Less
+      } else if token.start > i {
+        std::cmp::Ordering::Greater
+      } else {
+        std::cmp::Ordering::Equal
+      }
+    }) {
+      Ok(idx) => tokens[idx].kind,
+      Err(_) => TokenKind::Plain,
+    }
+  }
+}
+
+// Length of the string body including the closing delimiter, honoring backslash escapes.
+fn string_len(body: &str, delimiter: &str) -> usize {
+  let mut chars = body.char_indices();
+  while let Some((i, c)) = chars.next() {
+    if c == '\\' {
+      chars.next();
+    } else if body[i..].starts_with(delimiter) {
+      return i + delimiter.len();
+    }
+  }
+  body.len()
+}
+
+fn char_literal_len(rest: &str) -> Option<usize> {
+  let mut chars = rest.char_indices().skip(1);
+  let (_, c) = chars.next()?;
+  if c == '\\' {
+    chars.next()?;
+    return chars
+      .take(8)
+      .find(|(_, c)| *c == '\'')
+      .map(|(i, _)| i + 1);
+  }
+  match chars.next()? {
+    (i, '\'') => Some(i + 1),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinds<'a>(text: &'a str, language: &Language) -> std::vec::Vec<(TokenKind, &'a str)> {
+    tokenize(text, language)
+      .into_iter()
+      .filter(|token| token.kind != TokenKind::Plain)
+      .map(|token| (token.kind, &text[token.start..token.end]))
+      .collect()
+  }
+
+  #[test]
+  fn rust() {
+    let text = "fn f<'a>(x: &'a str) -> char { /* hi */ let c = '\\n'; \"s\\\"\" // end\n}";
+
+    assert_eq!(
+      kinds(text, &RUST),
+      vec![
+        (TokenKind::Keyword, "fn"),
+        (TokenKind::Comment, "/* hi */"),
+        (TokenKind::Keyword, "let"),
+        (TokenKind::String, "'\\n'"),
+        (TokenKind::String, "\"s\\\"\""),
+        (TokenKind::Comment, "// end"),
+      ]
+    );
+  }
+
+  #[test]
+  fn python() {
+    let text = "def f(x):\n    \"\"\"doc \"quoted\" \"\"\"\n    return x + 42  # answer";
+
+    assert_eq!(
+      kinds(text, &PYTHON),
+      vec![
+        (TokenKind::Keyword, "def"),
+        (TokenKind::String, "\"\"\"doc \"quoted\" \"\"\""),
+        (TokenKind::Keyword, "return"),
+        (TokenKind::Number, "42"),
+        (TokenKind::Comment, "# answer"),
+      ]
+    );
+  }
+
+  #[test]
+  fn javascript() {
+    let text = "const s = `a ${b}`; // done";
+
+    assert_eq!(
+      kinds(text, &JAVASCRIPT),
+      vec![
+        (TokenKind::Keyword, "const"),
+        (TokenKind::String, "`a ${b}`"),
+        (TokenKind::Comment, "// done"),
+      ]
+    );
+  }
+
+  #[test]
+  fn tokens_cover_text() {
+    let text = "let x = 'й'; // коментар\nx";
+    let tokens = tokenize(text, &RUST);
+
+    assert_eq!(tokens[0].start, 0);
+    assert_eq!(tokens.last().unwrap().end, text.len());
+    for pair in tokens.windows(2) {
+      assert_eq!(pair[0].end, pair[1].start);
+    }
+    assert_eq!(TokenKind::at(&tokens, text.find('й').unwrap()), TokenKind::String);
+    assert_eq!(TokenKind::at(&tokens, text.len()), TokenKind::Plain);
+  }
+
+  #[test]
+  fn detect_language() {
+    assert_eq!(detect("Rust/web-server", None).map(|l| l.name), Some("rust"));
+    assert_eq!(detect("Code/main.py", None).map(|l| l.name), Some("python"));
+    assert_eq!(detect("Basic/notes", Some("JavaScript")).map(|l| l.name), Some("javascript"));
+    assert_eq!(detect("Basic/lorem.txt", None).map(|l| l.name), None);
+  }
+}
@@ -0,0 +1,282 @@
+use crate::error::{Error, Result};
+
+// Where the game has always logged runs, relative to the working directory.
+const LOGFILE_PATH: &str = ".typeracer-log";
+
+// A log of runs, one line per record. The default one is the game's own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+  path: std::path::PathBuf,
+}
+
+pub struct Record {
+  pub time: u64,
+  pub accuracy: f32,
+  pub wpm: f32,
+  pub cpm: f32,
+  pub fields: std::vec::Vec<(String, String)>,
+}
+
+impl Record {
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .fields
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| &v[..])
+  }
+
+  fn to_line(&self) -> String {
+    let mut line = format!("{:?} {:?} {:?} {:?}", self.time, self.accuracy, self.wpm, self.cpm);
+    for (key, value) in &self.fields {
+      line.push(' ');
+      line.push_str(key);
+      line.push('=');
+      line.push_str(&escape(value));
+    }
+    line
+  }
+
+  fn from_line(line: &str) -> Option<Record> {
+    let mut parts = line.split(' ');
+
+    let (time, acc, wpm, cpm) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if let (Ok(time), Ok(accuracy), Ok(wpm), Ok(cpm)) =
+      (time.parse(), acc.parse(), wpm.parse(), cpm.parse())
+    {
+      let mut fields = vec![];
+      for part in parts {
+        let (key, value) = part.split_once('=')?;
+        fields.push((key.to_string(), unescape(value)));
+      }
+      Some(Record {
+        time,
+        accuracy,
+        wpm,
+        cpm,
+        fields,
+      })
+    } else {
+      None
+    }
+  }
+}
+
+impl History {
+  pub fn new(path: impl Into<std::path::PathBuf>) -> History {
+    History { path: path.into() }
+  }
+
+  pub fn path(&self) -> &std::path::Path {
+    &self.path
+  }
+
+  pub fn append(&self, record: &Record) -> Result<()> {
+    use std::io::Write;
+    let mut log_file = std::fs::OpenOptions::new()
+      .append(true)
+      .create(true)
+      .open(&self.path)
+      .map_err(Error::io("write history"))?;
+    log_file
+      .write_all(format!("{}\n", record.to_line()).as_bytes())
+      .map_err(Error::io("write history"))
+  }
+
+  // A missing log just means nothing has been played yet.
+  pub fn load(&self) -> Result<std::vec::Vec<Record>> {
+    let mut result = vec![];
+
+    let log_file = match std::fs::File::open(&self.path) {
+      Ok(log_file) => log_file,
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(result),
+      Err(error) => return Err(Error::io("read history")(error)),
+    };
+    let reader = std::io::BufReader::new(log_file);
+    use std::io::BufRead;
+    for (i, line) in reader.lines().enumerate() {
+      let line = line.map_err(Error::io("read history"))?;
+      result.push(Record::from_line(&line).ok_or(Error::InvalidHistory { line: i + 1 })?);
+    }
+    Ok(result)
+  }
+}
+
+impl Default for History {
+  fn default() -> History {
+    History::new(LOGFILE_PATH)
+  }
+}
+
+// A stable identifier of the typed content, so runs on the same text can be compared even if the
+// file is renamed or the text comes from a different passage split.
+pub fn text_id(content: &str) -> String {
+  // 64-bit FNV-1a, which unlike `DefaultHasher` is guaranteed to be stable between releases.
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in content.bytes() {
+    hash ^= byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  format!("{:016x}", hash)
+}
+
+pub fn best_wpm_by(records: &[Record], key: &str) -> std::collections::HashMap<String, f32> {
+  let mut best = std::collections::HashMap::new();
+  for record in records {
+    if let Some(text) = record.get(key) {
+      let wpm = best.entry(text.to_string()).or_insert(record.wpm);
+      if record.wpm > *wpm {
+        *wpm = record.wpm;
+      }
+    }
+  }
+  best
+}
+
+pub const SLOW_LETTERS: usize = 5;
+
+// The letters that most often appear among the slowest of recent runs.
+pub fn slow_letters(records: &[Record]) -> std::collections::HashSet<char> {
+  let mut counts = std::collections::HashMap::new();
+  for record in records.iter().rev().take(20) {
+    for c in record.get("slow").unwrap_or_default().chars() {
+      *counts.entry(c).or_insert(0) += 1;
+    }
+  }
+  let mut counts: std::vec::Vec<(char, usize)> = counts.into_iter().collect();
+  counts.sort_by_key(|(c, count)| (std::cmp::Reverse(*count), *c));
+  counts.into_iter().take(SLOW_LETTERS).map(|(c, _)| c).collect()
+}
+
+// Values are written space separated, so whitespace and the escape character itself are
+// percent-encoded.
+fn escape(value: &str) -> String {
+  let mut result = String::new();
+  for c in value.chars() {
+    match c {
+      '%' | ' ' | '\t' | '\r' | '\n' => result.push_str(&format!("%{:02X}", c as u32)),
+      _ => result.push(c),
+    }
+  }
+  result
+}
+
+fn unescape(value: &str) -> String {
+  let mut result = String::new();
+  let mut rest = value;
+  while let Some(i) = rest.find('%') {
+    result.push_str(&rest[..i]);
+    let code = rest
+      .get(i + 1..i + 3)
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    if let Some(code) = code {
+      result.push(code as char);
+      rest = &rest[i + 3..];
+    } else {
+      result.push('%');
+      rest = &rest[i + 1..];
+    }
+  }
+  result.push_str(rest);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn old_format() {
+    let record = Record::from_line("1614950000 0.95 61.2 305.5").unwrap();
+
+    assert_eq!(record.time, 1614950000);
+    assert!((record.cpm - 305.5).abs() < 0.0001);
+    assert!(record.fields.is_empty());
+  }
+
+  #[test]
+  fn fields_roundtrip() {
+    let record = Record {
+      time: 1,
+      accuracy: 0.5,
+      wpm: 10.,
+      cpm: 50.,
+      fields: vec![
+        (String::from("title"), String::from("100% a title")),
+        (String::from("text"), String::from("Basic/lorem.txt")),
+      ],
+    };
+
+    let parsed = Record::from_line(&record.to_line()).unwrap();
+
+    assert_eq!(parsed.fields, record.fields);
+    assert_eq!(parsed.get("title"), Some("100% a title"));
+    assert_eq!(parsed.get("author"), None);
+  }
+
+  #[test]
+  fn history_at_path() {
+    let dir = String::from("test_9c41d7e2b"); // random
+    std::fs::create_dir_all(&dir).unwrap();
+    let history = History::new(dir.clone() + "/log");
+    assert!(history.load().unwrap().is_empty());
+
+    for wpm in [40., 50.] {
+      let record = Record {
+        time: 1,
+        accuracy: 1.,
+        wpm,
+        cpm: wpm * 5.,
+        fields: vec![],
+      };
+      history.append(&record).unwrap();
+    }
+    let records = history.load().unwrap();
+    assert_eq!(records.len(), 2);
+    assert!((records[1].wpm - 50.).abs() < 0.0001);
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn best_wpm() {
+    let record = |text: &str, wpm: f32| Record {
+      time: 0,
+      accuracy: 1.,
+      wpm,
+      cpm: wpm * 5.,
+      fields: vec![(String::from("text"), String::from(text))],
+    };
+    let records = vec![record("a", 40.), record("b", 30.), record("a", 55.), record("a", 50.)];
+
+    let best = best_wpm_by(&records, "text");
+
+    assert_eq!(best.len(), 2);
+    assert!((best["a"] - 55.).abs() < 0.0001);
+    assert!((best["b"] - 30.).abs() < 0.0001);
+  }
+
+  #[test]
+  fn stable_text_id() {
+    assert_eq!(text_id(""), "cbf29ce484222325");
+    assert_eq!(text_id("a"), "af63dc4c8601ec8c");
+    assert_ne!(text_id("Hello"), text_id("Hello "));
+  }
+
+  #[test]
+  fn most_common_slow_letters() {
+    let record = |slow: &str| Record {
+      time: 0,
+      accuracy: 1.,
+      wpm: 50.,
+      cpm: 250.,
+      fields: vec![(String::from("slow"), String::from(slow))],
+    };
+    let records = vec![record("qzab"), record("qzcd"), record("qxef"), record("zxgh")];
+
+    let slow = slow_letters(&records);
+
+    assert_eq!(slow.len(), SLOW_LETTERS);
+    assert!(slow.contains(&'q') && slow.contains(&'z') && slow.contains(&'x'));
+  }
+}
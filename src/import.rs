@@ -0,0 +1,516 @@
+use crate::error::{Error, Result};
+use crate::metadata::Metadata;
+use crate::normalize::Normalization;
+use crate::passages::Segmentation;
+
+pub struct Book {
+  pub metadata: Metadata,
+  pub paragraphs: std::vec::Vec<String>,
+}
+
+// Reads a book and writes its passages as `<stem>-NNN` files into the category, replacing the
+// ones of an earlier import. Returns the number of passages written.
+pub fn import(
+  path: &std::path::Path,
+  texts_dir: &str,
+  category: &str,
+  segmentation: Segmentation,
+) -> Result<usize> {
+  let book = read_book(path)?;
+  let stem = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_else(|| String::from("imported"));
+  let title = book.metadata.title.clone().unwrap_or_else(|| stem.clone());
+
+  let normalization = Normalization {
+    collapse_whitespace: true,
+    ..Normalization::default()
+  };
+  let text = normalization.apply(&book.paragraphs.join("\n"));
+  let passages = crate::passages::split(&text, segmentation);
+  if passages.is_empty() {
+    return Err(invalid_book(path, "no readable text found"));
+  }
+
+  let category_path = std::path::Path::new(texts_dir).join(category);
+  std::fs::create_dir_all(&category_path)
+    .map_err(Error::io(format!("create {}", category_path.display())))?;
+  remove_passages(&category_path, &stem)?;
+  let width = passages.len().to_string().len().max(3);
+  for (i, passage) in passages.iter().enumerate() {
+    let metadata = Metadata {
+      title: Some(format!("{} ({}/{})", title, i + 1, passages.len())),
+      source: book
+        .metadata
+        .source
+        .clone()
+        .or_else(|| Some(path.to_string_lossy().to_string())),
+      ..book.metadata.clone()
+    };
+    let mut content = String::from("---\n");
+    for (key, value) in metadata.to_fields() {
+      content.push_str(&format!("{}: {}\n", key, value));
+    }
+    content.push_str("---\n");
+    content.push_str(passage);
+
+    let file_path = category_path.join(format!("{}-{:0width$}", stem, i + 1, width = width));
+    std::fs::write(&file_path, content)
+      .map_err(Error::io(format!("write {}", file_path.display())))?;
+  }
+  Ok(passages.len())
+}
+
+// Removes the `<stem>-NNN` files, so that none are left over when a book splits into fewer
+// passages than before.
+fn remove_passages(category_path: &std::path::Path, stem: &str) -> Result<()> {
+  let entries = std::fs::read_dir(category_path)
+    .map_err(Error::io(format!("read {}", category_path.display())))?;
+  for entry in entries.flatten() {
+    let name = entry.file_name().to_string_lossy().to_string();
+    let is_passage = name
+      .strip_prefix(stem)
+      .and_then(|rest| rest.strip_prefix('-'))
+      .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+    if is_passage {
+      std::fs::remove_file(entry.path())
+        .map_err(Error::io(format!("remove {}", entry.path().display())))?;
+    }
+  }
+  Ok(())
+}
+
+pub fn read_book(path: &std::path::Path) -> Result<Book> {
+  let read_to_string = |path: &std::path::Path| {
+    std::fs::read_to_string(path).map_err(Error::io(format!("read {}", path.display())))
+  };
+  let extension = path
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  match &extension[..] {
+    "epub" => read_epub(path),
+    "html" | "htm" | "xhtml" => {
+      let html = read_to_string(path)?;
+      Ok(Book {
+        metadata: Metadata {
+          title: html_title(&html),
+          ..Metadata::default()
+        },
+        paragraphs: html_to_paragraphs(&html),
+      })
+    }
+    "md" | "markdown" => {
+      let markdown = read_to_string(path)?;
+      let (front_matter, markdown) = Metadata::from_front_matter(&markdown);
+      Ok(Book {
+        metadata: front_matter.unwrap_or_default(),
+        paragraphs: markdown_to_paragraphs(markdown),
+      })
+    }
+    _ => {
+      let text = read_to_string(path)?;
+      let (front_matter, text) = Metadata::from_front_matter(&text);
+      Ok(Book {
+        metadata: front_matter.unwrap_or_default(),
+        paragraphs: text_to_paragraphs(text),
+      })
+    }
+  }
+}
+
+fn read_epub(path: &std::path::Path) -> Result<Book> {
+  let file = std::fs::File::open(path).map_err(Error::io(format!("read {}", path.display())))?;
+  let mut archive =
+    zip::ZipArchive::new(file).map_err(|error| invalid_book(path, &error.to_string()))?;
+  let mut read_entry = |name: &str| -> Result<String> {
+    use std::io::Read;
+    let mut entry = archive
+      .by_name(name)
+      .map_err(|error| invalid_book(path, &format!("{}: {}", name, error)))?;
+    let mut content = String::new();
+    entry
+      .read_to_string(&mut content)
+      .map_err(|error| invalid_book(path, &format!("{}: {}", name, error)))?;
+    Ok(content)
+  };
+
+  let container = read_entry("META-INF/container.xml")?;
+  let opf_path = find_tags(&container, "rootfile")
+    .into_iter()
+    .find_map(|tag| attribute(&tag, "full-path"))
+    .ok_or_else(|| invalid_book(path, "missing rootfile in container.xml"))?;
+  let opf = read_entry(&opf_path)?;
+  let opf_dir = std::path::Path::new(&opf_path)
+    .parent()
+    .map(|dir| dir.to_string_lossy().to_string())
+    .unwrap_or_default();
+
+  let manifest: std::collections::HashMap<String, String> = find_tags(&opf, "item")
+    .into_iter()
+    .filter_map(|tag| Some((attribute(&tag, "id")?, attribute(&tag, "href")?)))
+    .collect();
+
+  let mut paragraphs = vec![];
+  for itemref in find_tags(&opf, "itemref") {
+    let href = attribute(&itemref, "idref").and_then(|idref| manifest.get(&idref).cloned());
+    if let Some(href) = href {
+      let name = if opf_dir.is_empty() {
+        href
+      } else {
+        format!("{}/{}", opf_dir, href)
+      };
+      paragraphs.extend(html_to_paragraphs(&read_entry(&name)?));
+    }
+  }
+
+  Ok(Book {
+    metadata: Metadata {
+      title: element_text(&opf, "dc:title"),
+      author: element_text(&opf, "dc:creator"),
+      language: element_text(&opf, "dc:language"),
+      ..Metadata::default()
+    },
+    paragraphs,
+  })
+}
+
+fn text_to_paragraphs(text: &str) -> std::vec::Vec<String> {
+  crate::passages::split(text, Segmentation::Paragraph)
+    .into_iter()
+    .map(|paragraph| paragraph.split_whitespace().collect::<std::vec::Vec<_>>().join(" "))
+    .collect()
+}
+
+fn markdown_to_paragraphs(markdown: &str) -> std::vec::Vec<String> {
+  let mut text = String::new();
+  let mut in_code_block = false;
+  for line in markdown.lines() {
+    let line = line.trim();
+    if line.starts_with("```") || line.starts_with("~~~") {
+      in_code_block = !in_code_block;
+      continue;
+    }
+    if in_code_block || line.starts_with('|') || line.starts_with("---") {
+      text.push('\n');
+      continue;
+    }
+    let line = line.trim_start_matches('#').trim_start_matches('>').trim_start();
+    let line = line
+      .strip_prefix("- ")
+      .or_else(|| line.strip_prefix("* "))
+      .or_else(|| line.strip_prefix("+ "))
+      .unwrap_or(line);
+    text.push_str(&strip_markdown_inline(line));
+    text.push('\n');
+  }
+  text_to_paragraphs(&text)
+}
+
+// Drops emphasis and code markers and keeps only the text of links; images are removed.
+fn strip_markdown_inline(line: &str) -> String {
+  let mut result = String::new();
+  let mut rest = line;
+  while let Some(c) = rest.chars().next() {
+    if c == '!' && rest[1..].starts_with('[') {
+      if let Some(end) = rest.find(')') {
+        rest = &rest[end + 1..];
+        continue;
+      }
+    }
+    if c == '[' {
+      if let (Some(close), Some(end)) = (rest.find("]("), rest.find(')')) {
+        if close < end {
+          result.push_str(&rest[1..close]);
+          rest = &rest[end + 1..];
+          continue;
+        }
+      }
+    }
+    if !matches!(c, '*' | '_' | '`') {
+      result.push(c);
+    }
+    rest = &rest[c.len_utf8()..];
+  }
+  result
+}
+
+const BLOCK_TAGS: [&str; 16] = [
+  "p", "div", "br", "li", "h1", "h2", "h3", "h4", "h5", "h6", "tr", "blockquote", "section",
+  "article", "pre", "hr",
+];
+const SKIPPED_TAGS: [&str; 4] = ["head", "script", "style", "title"];
+
+fn html_to_paragraphs(html: &str) -> std::vec::Vec<String> {
+  let mut text = String::new();
+  let mut skipping: Option<String> = None;
+  let mut rest = html;
+  while let Some(start) = rest.find('<') {
+    if skipping.is_none() {
+      text.push_str(&decode_entities(&rest[..start]));
+    }
+    let end = match rest[start..].find('>') {
+      Some(end) => start + end,
+      None => break,
+    };
+    let tag = &rest[start + 1..end];
+    let is_closing = tag.starts_with('/');
+    let name = tag
+      .trim_start_matches('/')
+      .split(|c: char| c.is_whitespace() || c == '/')
+      .next()
+      .unwrap_or("")
+      .to_lowercase();
+
+    match &skipping {
+      Some(skipped) => {
+        if is_closing && *skipped == name {
+          skipping = None;
+        }
+      }
+      None => {
+        if !is_closing && !tag.ends_with('/') && SKIPPED_TAGS.contains(&&name[..]) {
+          skipping = Some(name);
+        } else if BLOCK_TAGS.contains(&&name[..]) {
+          text.push_str("\n\n");
+        }
+      }
+    }
+    rest = &rest[end + 1..];
+  }
+  if skipping.is_none() {
+    text.push_str(&decode_entities(rest));
+  }
+  text_to_paragraphs(&text)
+}
+
+fn html_title(html: &str) -> Option<String> {
+  element_text(html, "title")
+}
+
+fn decode_entities(text: &str) -> String {
+  let mut result = String::new();
+  let mut rest = text;
+  while let Some(start) = rest.find('&') {
+    result.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let end = match rest.find(';') {
+      Some(end) if end <= 10 => end,
+      _ => {
+        result.push('&');
+        rest = &rest[1..];
+        continue;
+      }
+    };
+    let entity = &rest[1..end];
+    let decoded = match entity {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      "nbsp" => Some('\u{a0}'),
+      "mdash" => Some('—'),
+      "ndash" => Some('–'),
+      "hellip" => Some('…'),
+      _ => {
+        if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+          u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32)
+        } else if let Some(decimal) = entity.strip_prefix('#') {
+          decimal.parse().ok().and_then(std::char::from_u32)
+        } else {
+          None
+        }
+      }
+    };
+    match decoded {
+      Some(c) => {
+        result.push(c);
+        rest = &rest[end + 1..];
+      }
+      None => {
+        result.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+  result.push_str(rest);
+  result
+}
+
+// Returns the contents of every `<name ...>` tag, without the angle brackets.
+fn find_tags(xml: &str, name: &str) -> std::vec::Vec<String> {
+  let mut tags = vec![];
+  let open = format!("<{}", name);
+  let mut rest = xml;
+  while let Some(start) = rest.find(&open) {
+    let after = &rest[start + open.len()..];
+    let end = match after.find('>') {
+      Some(end) => end,
+      None => break,
+    };
+    if after.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+      tags.push(after[..end].to_string());
+    }
+    rest = &after[end..];
+  }
+  tags
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+  let mut rest = tag;
+  loop {
+    let start = rest.find(name)?;
+    let preceded_by_space = rest[..start].ends_with(|c: char| c.is_whitespace()) || start == 0;
+    let after = rest[start + name.len()..].trim_start();
+    rest = &rest[start + name.len()..];
+    if !preceded_by_space {
+      continue;
+    }
+    if let Some(value) = after.strip_prefix('=') {
+      let value = value.trim_start();
+      let quote = value.chars().next()?;
+      let value = &value[1..];
+      let end = value.find(quote)?;
+      return Some(decode_entities(&value[..end]));
+    }
+  }
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+  let open = format!("<{}", name);
+  let start = xml.find(&open)?;
+  let content_start = start + xml[start..].find('>')? + 1;
+  let end = content_start + xml[content_start..].find(&format!("</{}", name))?;
+  let text = decode_entities(&xml[content_start..end]);
+  let text = text.split_whitespace().collect::<std::vec::Vec<_>>().join(" ");
+  if text.is_empty() {
+    None
+  } else {
+    Some(text)
+  }
+}
+
+fn invalid_book(path: &std::path::Path, message: &str) -> Error {
+  Error::InvalidBook {
+    path: path.display().to_string(),
+    message: message.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn html() {
+    let html = "<html><head><title>A &amp; B</title><style>p {}</style></head>\
+      <body><h1>Chapter&nbsp;1</h1><p>First\n  paragraph &#8212; <em>here</em>.</p>\
+      <script>var x = 1;</script><p>Second<br/>line</p></body></html>";
+
+    assert_eq!(html_title(html).as_deref(), Some("A & B"));
+    assert_eq!(
+      html_to_paragraphs(html),
+      vec!["Chapter 1", "First paragraph \u{2014} here.", "Second", "line"]
+    );
+  }
+
+  #[test]
+  fn markdown() {
+    let markdown = "# Title\n\nSome *bold* and [a link](http://x.y) ![img](a.png)\ncontinued.\n\n\
+      ```\ncode\n```\n- item `one`\n";
+
+    assert_eq!(
+      markdown_to_paragraphs(markdown),
+      vec!["Title", "Some bold and a link continued.", "item one"]
+    );
+  }
+
+  #[test]
+  fn xml_helpers() {
+    let opf = "<package><metadata><dc:title id=\"t\">The  Book</dc:title></metadata>\
+      <manifest><item id=\"c1\" href=\"ch1.xhtml\" media-type=\"x\"/></manifest>\
+      <spine><itemref idref=\"c1\"/></spine></package>";
+
+    assert_eq!(element_text(opf, "dc:title").as_deref(), Some("The Book"));
+    let items = find_tags(opf, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(attribute(&items[0], "id").as_deref(), Some("c1"));
+    assert_eq!(attribute(&items[0], "href").as_deref(), Some("ch1.xhtml"));
+    assert_eq!(attribute(&find_tags(opf, "itemref")[0], "idref").as_deref(), Some("c1"));
+  }
+
+  #[test]
+  fn import_epub() {
+    let dir = String::from("test_9a4c2e7f1"); // random
+    std::fs::create_dir(dir.clone()).unwrap();
+    let epub_path = std::path::Path::new(&dir).join("book.epub");
+    {
+      use std::io::Write;
+      let mut zip = zip::ZipWriter::new(std::fs::File::create(&epub_path).unwrap());
+      let options = zip::write::SimpleFileOptions::default();
+      let files = [
+        (
+          "META-INF/container.xml",
+          "<container><rootfiles><rootfile full-path=\"OEBPS/content.opf\"/></rootfiles></container>",
+        ),
+        (
+          "OEBPS/content.opf",
+          "<package><metadata><dc:title>Tiny</dc:title><dc:creator>Me</dc:creator></metadata>\
+           <manifest><item id=\"a\" href=\"a.xhtml\"/><item id=\"b\" href=\"b.xhtml\"/></manifest>\
+           <spine><itemref idref=\"b\"/><itemref idref=\"a\"/></spine></package>",
+        ),
+        ("OEBPS/a.xhtml", "<html><body><p>Second chapter.</p></body></html>"),
+        ("OEBPS/b.xhtml", "<html><body><p>First chapter.</p></body></html>"),
+      ];
+      for (name, content) in files.iter() {
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+      }
+      zip.finish().unwrap();
+    }
+
+    let texts_dir = dir.clone() + "/texts";
+    let count = import(&epub_path, &texts_dir, "Books", Segmentation::Paragraph).unwrap();
+
+    assert_eq!(count, 2);
+    let c = crate::categories::Categories::new(texts_dir.clone());
+    let first = c.get_text_by_name("Books/book-001").unwrap();
+    assert_eq!(first.content, "First chapter.");
+    assert_eq!(first.metadata.title.as_deref(), Some("Tiny (1/2)"));
+    assert_eq!(first.metadata.author.as_deref(), Some("Me"));
+
+    // Importing again in fewer passages leaves none of the old ones behind.
+    let other = texts_dir.clone() + "/Books/book-notes";
+    std::fs::write(&other, "Kept.").unwrap();
+    let count = import(&epub_path, &texts_dir, "Books", Segmentation::Length(100)).unwrap();
+    assert_eq!(count, 1);
+    let mut names: std::vec::Vec<String> = std::fs::read_dir(texts_dir.clone() + "/Books")
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+      .collect();
+    names.sort();
+    assert_eq!(names, vec!["book-001", "book-notes"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn import_invalid_epub() {
+    let dir = String::from("test_3f81b6d02"); // random
+    std::fs::create_dir(dir.clone()).unwrap();
+    let epub_path = std::path::Path::new(&dir).join("book.epub");
+    std::fs::write(&epub_path, "not a zip").unwrap();
+
+    let texts_dir = dir.clone() + "/texts";
+    let result = import(&epub_path, &texts_dir, "Books", Segmentation::Paragraph);
+    assert!(matches!(result, Err(Error::InvalidBook { .. })));
+    let missing = std::path::Path::new(&dir).join("missing.txt");
+    let result = import(&missing, &texts_dir, "Books", Segmentation::Paragraph);
+    assert!(matches!(result, Err(Error::Io { .. })));
+    assert!(!std::path::Path::new(&texts_dir).exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -0,0 +1,166 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// Tab stops when drawing, the way terminals put them.
+pub const TAB_STOP: usize = 8;
+
+// A byte range of the text shown on one row. Lines cover the text one after another, and a line
+// that ends a paragraph includes its newline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+  pub start: usize,
+  pub end: usize,
+}
+
+// The text broken into lines of at most `width` columns, between words where possible.
+pub struct Layout {
+  width: usize,
+  lines: std::vec::Vec<Line>,
+}
+
+// Columns taken by a grapheme that starts at `column`. Newlines take one, so that there is room
+// to show the cursor on them, and so do graphemes that would take none, so that they can be seen.
+pub fn grapheme_width(grapheme: &str, column: usize) -> usize {
+  if grapheme == "\t" {
+    TAB_STOP - column % TAB_STOP
+  } else {
+    grapheme.width().clamp(1, 2)
+  }
+}
+
+// Like `grapheme_width`, but whitespace only goes up to the end of a line `width` columns wide.
+// Past the end, it hangs there without taking any columns, rather than starting the next line.
+fn width_in_line(grapheme: &str, column: usize, width: usize) -> usize {
+  let columns = grapheme_width(grapheme, column);
+  if grapheme.chars().all(char::is_whitespace) {
+    columns.min(width.saturating_sub(column))
+  } else {
+    columns
+  }
+}
+
+impl Layout {
+  pub fn new(text: &str, width: usize) -> Layout {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut column = 0;
+    // Where the word being laid out starts, with its spaces after it, and the column there.
+    let mut word = (0, 0);
+    let mut in_space = false;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+      let is_space = grapheme.chars().all(char::is_whitespace);
+      if in_space && !is_space {
+        word = (offset, column);
+      }
+      in_space = is_space;
+
+      if column + width_in_line(grapheme, column, width) > width {
+        // Words wider than a line are broken where they stop fitting.
+        let (end, word_column) = if word.0 > start { word } else { (offset, column) };
+        lines.push(Line { start, end });
+        start = end;
+        column -= word_column;
+        word = (start, 0);
+      }
+      column += width_in_line(grapheme, column, width);
+
+      if grapheme.ends_with('\n') {
+        lines.push(Line {
+          start,
+          end: offset + grapheme.len(),
+        });
+        start = offset + grapheme.len();
+        column = 0;
+        word = (start, 0);
+        in_space = false;
+      }
+    }
+    if start < text.len() || lines.last().is_none_or(|line| text[..line.end].ends_with('\n')) {
+      lines.push(Line {
+        start,
+        end: text.len(),
+      });
+    }
+    Layout { width, lines }
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  // Columns a grapheme at `column` of one of the lines takes.
+  pub fn grapheme_width(&self, grapheme: &str, column: usize) -> usize {
+    width_in_line(grapheme, column, self.width)
+  }
+
+  pub fn lines(&self) -> &[Line] {
+    &self.lines
+  }
+
+  // The line with the byte at `offset`, or the last line for the end of the text.
+  pub fn line_at(&self, offset: usize) -> usize {
+    self
+      .lines
+      .partition_point(|line| line.start <= offset)
+      .saturating_sub(1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lines(text: &str, width: usize) -> std::vec::Vec<&str> {
+    let layout = Layout::new(text, width);
+    layout.lines().iter().map(|line| &text[line.start..line.end]).collect()
+  }
+
+  #[test]
+  fn wraps_between_words() {
+    assert_eq!(lines("one two three", 8), vec!["one two ", "three"]);
+    assert_eq!(lines("one two three", 6), vec!["one ", "two ", "three"]);
+    assert_eq!(lines("abcdefghij kl", 4), vec!["abcd", "efgh", "ij ", "kl"]);
+  }
+
+  #[test]
+  fn keeps_paragraphs() {
+    assert_eq!(lines("one\n\ntwo three\n", 6), vec!["one\n", "\n", "two ", "three\n", ""]);
+    assert_eq!(lines("ab cd\nef", 5), vec!["ab cd\n", "ef"]);
+  }
+
+  #[test]
+  fn display_width() {
+    assert_eq!(lines("中文 字", 4), vec!["中文 ", "字"]);
+    assert_eq!(lines("e\u{301}e\u{301} x", 3), vec!["e\u{301}e\u{301} ", "x"]);
+  }
+
+  #[test]
+  fn tabs_fit_in_lines() {
+    assert_eq!(lines("a\tb c", 4), vec!["a\t", "b c"]);
+    assert_eq!(lines("abcd\tx", 4), vec!["abcd\t", "x"]);
+
+    let layout = Layout::new("a\tb c", 4);
+    assert_eq!(layout.grapheme_width("\t", 1), 3);
+    assert_eq!(layout.grapheme_width("\t", 0), 4);
+    assert_eq!(layout.grapheme_width("中", 3), 2);
+  }
+
+  #[test]
+  fn spaces_hang_after_full_lines() {
+    assert_eq!(lines("hello world foo", 5), vec!["hello ", "world ", "foo"]);
+    assert_eq!(lines("ab cd ef", 2), vec!["ab ", "cd ", "ef"]);
+    assert_eq!(lines("ab  cd", 2), vec!["ab  ", "cd"]);
+    assert_eq!(Layout::new("ab cd", 2).grapheme_width(" ", 2), 0);
+  }
+
+  #[test]
+  fn finds_lines() {
+    let layout = Layout::new("one two\nthree", 5);
+
+    assert_eq!(layout.line_at(0), 0);
+    assert_eq!(layout.line_at(4), 1);
+    assert_eq!(layout.line_at(8), 2);
+    assert_eq!(layout.line_at(13), 2);
+  }
+}
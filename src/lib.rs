@@ -0,0 +1,30 @@
+//! Texts, typing sessions and run history for typing-racer, without any terminal code.
+
+mod categories;
+mod clock;
+mod collection;
+mod compose;
+mod difficulty;
+mod error;
+mod highlight;
+pub mod history;
+mod import;
+mod layout;
+mod metadata;
+mod normalize;
+mod passages;
+mod settings;
+mod starter;
+mod text;
+
+pub use categories::{Categories, Text, TextEntry};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use difficulty::Level;
+pub use error::{Error, Result};
+pub use highlight::{Token, TokenKind};
+pub use history::{History, Record};
+pub use import::import;
+pub use layout::{grapheme_width, Layout, Line, TAB_STOP};
+pub use metadata::Metadata;
+pub use passages::{Passage, Segmentation};
+pub use text::{Aligned, ErrorMode, InputMode, LetterStats, Summary, TextManager};
@@ -1,9 +1,96 @@
-mod categories;
-mod text;
+mod backend;
 mod ui;
 
+use typing_racer::{ErrorMode, Segmentation};
+
+const USAGE: &str = "Usage:
+  typing-racer [TEXTS_DIR] [--width COLUMNS] [--caret block|underline|bar|smooth]
+    [--errors expected|typed|below] [--error-mode free|stop|word] [--skip-words]
+  typing-racer import FILE CATEGORY [TEXTS_DIR] [--passages paragraph|items|sentence|LENGTH]";
+
 fn main() {
   let args: Vec<_> = std::env::args().collect();
 
-  ui::UI::new(if args.len() > 1 { args[1].clone() } else { String::from("./texts") }).run();
-}
\ No newline at end of file
+  if args.len() > 1 && args[1] == "import" {
+    std::process::exit(import(&args[2..]));
+  }
+  if args.len() > 1 && (args[1] == "--help" || args[1] == "-h") {
+    println!("{}", USAGE);
+    return;
+  }
+
+  let (texts_dir, options) = match parse_options(&args[1..]) {
+    Some(parsed) => parsed,
+    None => {
+      eprintln!("{}", USAGE);
+      std::process::exit(2);
+    }
+  };
+  match ui::UI::new(texts_dir, options) {
+    Ok(mut ui) => ui.run(),
+    Err(error) => {
+      eprintln!("Could not start: {}", error);
+      std::process::exit(1);
+    }
+  }
+}
+
+fn parse_options(args: &[String]) -> Option<(String, ui::Options)> {
+  let mut texts_dir = None;
+  let mut options = ui::Options::default();
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match &arg[..] {
+      "--width" => {
+        let width = args.next()?.parse::<usize>().ok().filter(|width| *width > 0)?;
+        options.line_width = Some(width);
+      }
+      "--caret" => options.caret = ui::Caret::parse(args.next()?)?,
+      "--errors" => options.errors = ui::Errors::parse(args.next()?)?,
+      "--error-mode" => options.error_mode = ErrorMode::parse(args.next()?)?,
+      "--skip-words" => options.skip_words = true,
+      _ if texts_dir.is_none() => texts_dir = Some(arg.clone()),
+      _ => return None,
+    }
+  }
+  Some((texts_dir.unwrap_or_else(|| String::from("./texts")), options))
+}
+
+fn import(args: &[String]) -> i32 {
+  let mut positional = vec![];
+  let mut segmentation = Segmentation::Length(500);
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    if arg == "--passages" {
+      match args.next().and_then(|value| Segmentation::parse(value)) {
+        Some(value) => segmentation = value,
+        None => {
+          eprintln!("{}", USAGE);
+          return 2;
+        }
+      }
+    } else {
+      positional.push(arg);
+    }
+  }
+
+  let (file, category, texts_dir) = match positional[..] {
+    [file, category] => (file, category, "./texts"),
+    [file, category, texts_dir] => (file, category, &texts_dir[..]),
+    _ => {
+      eprintln!("{}", USAGE);
+      return 2;
+    }
+  };
+
+  match typing_racer::import(std::path::Path::new(file), texts_dir, category, segmentation) {
+    Ok(count) => {
+      println!("Imported {} passages into {}", count, category);
+      0
+    }
+    Err(error) => {
+      eprintln!("Could not import: {}", error);
+      1
+    }
+  }
+}
@@ -0,0 +1,169 @@
+use crate::error::{Error, Result};
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+pub const SIDECAR_EXTENSION: &str = "meta";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+  pub title: Option<String>,
+  pub author: Option<String>,
+  pub source: Option<String>,
+  pub language: Option<String>,
+  pub difficulty: Option<String>,
+  pub tags: std::vec::Vec<String>,
+}
+
+impl Metadata {
+  // Splits an optional `---` delimited front matter block off the start of the text.
+  pub fn from_front_matter(text: &str) -> (Option<Metadata>, &str) {
+    let mut lines = text.split_inclusive('\n');
+    match lines.next() {
+      Some(first) if first.trim_end() == FRONT_MATTER_DELIMITER => {}
+      _ => return (None, text),
+    }
+
+    let mut offset = text.find('\n').unwrap_or(text.len()) + 1;
+    let header_start = offset;
+    for line in lines {
+      if line.trim_end() == FRONT_MATTER_DELIMITER {
+        let metadata = Self::from_fields(&text[header_start..offset]);
+        return (Some(metadata), &text[offset + line.len()..]);
+      }
+      offset += line.len();
+    }
+
+    (None, text)
+  }
+
+  pub fn from_fields(fields: &str) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (key, value) in parse_fields(fields) {
+      match &key[..] {
+        "title" => metadata.title = Some(value),
+        "author" => metadata.author = Some(value),
+        "source" | "url" => metadata.source = Some(value),
+        "language" | "lang" => metadata.language = Some(value),
+        "difficulty" => metadata.difficulty = Some(value),
+        "tags" => {
+          metadata.tags = value
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+        }
+        _ => (),
+      }
+    }
+    metadata
+  }
+
+  // Texts without a sidecar have no metadata, but an unreadable sidecar is an error.
+  pub fn read_sidecar(text_path: &std::path::Path) -> Result<Option<Metadata>> {
+    let mut sidecar_name = match text_path.file_name() {
+      Some(name) => name.to_os_string(),
+      None => return Ok(None),
+    };
+    sidecar_name.push(".");
+    sidecar_name.push(SIDECAR_EXTENSION);
+    let sidecar_path = text_path.with_file_name(sidecar_name);
+    match std::fs::read_to_string(&sidecar_path) {
+      Ok(fields) => Ok(Some(Self::from_fields(&fields))),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+      Err(error) => Err(Error::io(format!("read {}", sidecar_path.display()))(error)),
+    }
+  }
+
+  // Fields set in `other` take precedence over the ones in `self`.
+  pub fn merge(self, other: Metadata) -> Metadata {
+    Metadata {
+      title: other.title.or(self.title),
+      author: other.author.or(self.author),
+      source: other.source.or(self.source),
+      language: other.language.or(self.language),
+      difficulty: other.difficulty.or(self.difficulty),
+      tags: if other.tags.is_empty() { self.tags } else { other.tags },
+    }
+  }
+
+  pub fn to_fields(&self) -> std::vec::Vec<(String, String)> {
+    let mut fields = vec![];
+    let optional = [
+      ("title", &self.title),
+      ("author", &self.author),
+      ("source", &self.source),
+      ("language", &self.language),
+      ("difficulty", &self.difficulty),
+    ];
+    for (key, value) in optional.iter() {
+      if let Some(value) = value {
+        fields.push((key.to_string(), value.clone()));
+      }
+    }
+    if !self.tags.is_empty() {
+      fields.push((String::from("tags"), self.tags.join(",")));
+    }
+    fields
+  }
+}
+
+// Parses `key: value` lines, ignoring blank lines and `#` comments.
+pub fn parse_fields(text: &str) -> std::vec::Vec<(String, String)> {
+  let mut fields = vec![];
+  for line in text.lines() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    if let Some((key, value)) = line.split_once(':') {
+      fields.push((key.trim().to_lowercase(), value.trim().to_string()));
+    }
+  }
+  fields
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn front_matter() {
+    let (metadata, content) = Metadata::from_front_matter(
+      "---\ntitle: The Book\nauthor: Someone\ntags: fiction, short\n---\nOnce upon a time",
+    );
+
+    let metadata = metadata.unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("The Book"));
+    assert_eq!(metadata.author.as_deref(), Some("Someone"));
+    assert_eq!(metadata.tags, vec!["fiction", "short"]);
+    assert_eq!(metadata.source, None);
+    assert_eq!(content, "Once upon a time");
+  }
+
+  #[test]
+  fn no_front_matter() {
+    let text = "title: not metadata\n---\n";
+    let (metadata, content) = Metadata::from_front_matter(text);
+
+    assert_eq!(metadata, None);
+    assert_eq!(content, text);
+  }
+
+  #[test]
+  fn unterminated_front_matter() {
+    let text = "---\ntitle: Missing end\nSome text";
+    let (metadata, content) = Metadata::from_front_matter(text);
+
+    assert_eq!(metadata, None);
+    assert_eq!(content, text);
+  }
+
+  #[test]
+  fn merge() {
+    let sidecar = Metadata::from_fields("title: Sidecar\nsource: https://example.com\n");
+    let front_matter = Metadata::from_fields("title: Front matter\n");
+    let metadata = sidecar.merge(front_matter);
+
+    assert_eq!(metadata.title.as_deref(), Some("Front matter"));
+    assert_eq!(metadata.source.as_deref(), Some("https://example.com"));
+  }
+}
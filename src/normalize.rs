@@ -0,0 +1,153 @@
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Normalization {
+  pub line_endings: bool,
+  pub fold_quotes: bool,
+  pub collapse_whitespace: bool,
+  pub tab_width: Option<usize>,
+  pub strip_diacritics: bool,
+  pub nfc: bool,
+}
+
+impl Default for Normalization {
+  fn default() -> Normalization {
+    Normalization {
+      line_endings: true,
+      fold_quotes: true,
+      collapse_whitespace: false,
+      tab_width: Some(4),
+      strip_diacritics: false,
+      nfc: true,
+    }
+  }
+}
+
+impl Normalization {
+  pub fn apply(&self, text: &str) -> String {
+    let mut text = String::from(text);
+    if self.line_endings {
+      text = text.replace("\r\n", "\n").replace('\r', "\n");
+    }
+    if self.nfc {
+      text = text.nfc().collect();
+    }
+    if self.fold_quotes {
+      text = fold_typography(&text);
+    }
+    if let Some(tab_width) = self.tab_width {
+      text = expand_tabs(&text, tab_width);
+    }
+    if self.collapse_whitespace {
+      text = collapse_whitespace(&text);
+    }
+    if self.strip_diacritics {
+      text = text
+        .nfd()
+        .filter(|c| !unicode_normalization::char::is_combining_mark(*c))
+        .nfc()
+        .collect();
+    }
+    text
+  }
+}
+
+// Replaces characters that are missing from most keyboard layouts with their closest ASCII form.
+fn fold_typography(text: &str) -> String {
+  let mut result = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '‘' | '’' | '‚' | '‛' | '′' => result.push('\''),
+      '“' | '”' | '„' | '‟' | '″' | '«' | '»' => result.push('"'),
+      '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => result.push('-'),
+      '…' => result.push_str("..."),
+      '\u{a0}' | '\u{2007}' | '\u{2009}' | '\u{200a}' | '\u{202f}' => result.push(' '),
+      '\u{ad}' | '\u{200b}' | '\u{feff}' => (),
+      _ => result.push(c),
+    }
+  }
+  result
+}
+
+fn expand_tabs(text: &str, tab_width: usize) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut column = 0;
+  for c in text.chars() {
+    match c {
+      '\t' => {
+        let spaces = if tab_width == 0 { 0 } else { tab_width - column % tab_width };
+        result.extend(std::iter::repeat_n(' ', spaces));
+        column += spaces;
+      }
+      '\n' => {
+        result.push(c);
+        column = 0;
+      }
+      _ => {
+        result.push(c);
+        column += 1;
+      }
+    }
+  }
+  result
+}
+
+// Collapses runs of spaces and tabs into a single space and drops trailing whitespace on each line.
+fn collapse_whitespace(text: &str) -> String {
+  let lines: std::vec::Vec<String> = text
+    .split('\n')
+    .map(|line| {
+      let words: std::vec::Vec<&str> =
+        line.split([' ', '\t']).filter(|word| !word.is_empty()).collect();
+      words.join(" ")
+    })
+    .collect();
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_normalization() {
+    let text = "\u{201e}Hi\u{201c} \u{2014} it\u{2019}s\u{a0}me\u{2026}\r\n\tnext";
+
+    assert_eq!(
+      Normalization::default().apply(text),
+      "\"Hi\" - it's me...\n    next"
+    );
+  }
+
+  #[test]
+  fn nfc() {
+    let decomposed = "e\u{301}";
+
+    assert_eq!(Normalization::default().apply(decomposed), "\u{e9}");
+  }
+
+  #[test]
+  fn tabs_to_tab_stops() {
+    assert_eq!(expand_tabs("a\tb\n\tc", 4), "a   b\n    c");
+  }
+
+  #[test]
+  fn collapse() {
+    let normalization = Normalization {
+      collapse_whitespace: true,
+      ..Normalization::default()
+    };
+
+    assert_eq!(normalization.apply("  a   b  \nc\t d "), "a b\nc d");
+  }
+
+  #[test]
+  fn strip_diacritics() {
+    let normalization = Normalization {
+      strip_diacritics: true,
+      ..Normalization::default()
+    };
+
+    assert_eq!(normalization.apply("Crème brûlée"), "Creme brulee");
+  }
+}
@@ -0,0 +1,214 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segmentation {
+  Whole,
+  Paragraph,
+  // For code: paragraphs that start at the left margin, so that blocks are kept whole.
+  Items,
+  Sentence,
+  Length(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Passage {
+  pub index: usize,
+  pub count: usize,
+}
+
+impl Segmentation {
+  pub fn parse(value: &str) -> Option<Segmentation> {
+    match &value.trim().to_lowercase()[..] {
+      "whole" | "none" => Some(Segmentation::Whole),
+      "paragraph" => Some(Segmentation::Paragraph),
+      "items" => Some(Segmentation::Items),
+      "sentence" => Some(Segmentation::Sentence),
+      value => value
+        .parse()
+        .ok()
+        .filter(|length| *length > 0)
+        .map(Segmentation::Length),
+    }
+  }
+}
+
+pub fn split(text: &str, segmentation: Segmentation) -> std::vec::Vec<&str> {
+  let passages = match segmentation {
+    Segmentation::Whole => vec![text],
+    Segmentation::Paragraph => paragraphs(text),
+    Segmentation::Items => items(text),
+    Segmentation::Sentence => sentences(text),
+    Segmentation::Length(length) => join_to_length(text, sentences(text), length),
+  };
+  passages
+    .into_iter()
+    .map(trim_blank_lines)
+    .filter(|passage| !passage.is_empty())
+    .collect()
+}
+
+// Trims the blank lines around a passage and the whitespace at its end, but keeps the
+// indentation of its first line.
+fn trim_blank_lines(passage: &str) -> &str {
+  let mut start = 0;
+  for line in passage.split_inclusive('\n') {
+    if !line.trim().is_empty() {
+      break;
+    }
+    start += line.len();
+  }
+  passage[start..].trim_end()
+}
+
+// Paragraphs are separated by blank lines. Texts without blank lines keep a paragraph per line.
+fn paragraphs(text: &str) -> std::vec::Vec<&str> {
+  let has_blank_lines = text.trim().lines().any(|line| line.trim().is_empty());
+  let mut result = vec![];
+  let mut start = 0;
+  let mut offset = 0;
+  for line in text.split_inclusive('\n') {
+    let is_boundary = if has_blank_lines {
+      line.trim().is_empty()
+    } else {
+      true
+    };
+    offset += line.len();
+    if is_boundary {
+      result.push(&text[start..offset]);
+      start = offset;
+    }
+  }
+  result.push(&text[start..]);
+  result
+}
+
+// Like paragraphs, but only blank lines before an unindented line end one.
+fn items(text: &str) -> std::vec::Vec<&str> {
+  let mut result = vec![];
+  let mut start = 0;
+  let mut offset = 0;
+  let mut after_blank_line = false;
+  for line in text.split_inclusive('\n') {
+    let is_blank = line.trim().is_empty();
+    if after_blank_line && !is_blank && !line.starts_with(char::is_whitespace) {
+      result.push(&text[start..offset]);
+      start = offset;
+    }
+    after_blank_line = is_blank;
+    offset += line.len();
+  }
+  result.push(&text[start..]);
+  result
+}
+
+// Sentences leave out the spaces between them.
+fn sentences(text: &str) -> std::vec::Vec<&str> {
+  let mut result = vec![];
+  for paragraph in paragraphs(text) {
+    let mut start = 0;
+    let mut after_terminator = false;
+    for (i, c) in paragraph.char_indices() {
+      if after_terminator && c.is_whitespace() {
+        result.push(paragraph[start..i].trim_start());
+        start = i;
+        after_terminator = false;
+      } else if matches!(c, '.' | '!' | '?' | '…') {
+        after_terminator = true;
+      } else if !matches!(c, '"' | '\'' | ')' | '»' | '”' | '“') {
+        after_terminator = false;
+      }
+    }
+    result.push(paragraph[start..].trim_start());
+  }
+  result
+}
+
+// Greedily joins consecutive pieces until each passage reaches the target number of characters.
+fn join_to_length<'a>(
+  text: &'a str,
+  pieces: std::vec::Vec<&'a str>,
+  length: usize,
+) -> std::vec::Vec<&'a str> {
+  let mut result = vec![];
+  let mut current: Option<(usize, usize)> = None;
+  for piece in pieces {
+    let piece_start = piece.as_ptr() as usize - text.as_ptr() as usize;
+    let piece_end = piece_start + piece.len();
+    let (start, _) = current.unwrap_or((piece_start, piece_start));
+    if text[start..piece_end].trim().chars().count() >= length {
+      result.push(&text[start..piece_end]);
+      current = None;
+    } else {
+      current = Some((start, piece_end));
+    }
+  }
+  if let Some((start, end)) = current {
+    result.push(&text[start..end]);
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse() {
+    assert_eq!(Segmentation::parse("Paragraph"), Some(Segmentation::Paragraph));
+    assert_eq!(Segmentation::parse("300"), Some(Segmentation::Length(300)));
+    assert_eq!(Segmentation::parse("0"), None);
+    assert_eq!(Segmentation::parse("chapters"), None);
+  }
+
+  #[test]
+  fn paragraphs_by_line() {
+    let text = "First line.\nSecond line.\n\n";
+
+    assert_eq!(
+      split(text, Segmentation::Paragraph),
+      vec!["First line.", "Second line."]
+    );
+  }
+
+  #[test]
+  fn paragraphs_by_blank_line() {
+    let text = "fn main() {\n  run();\n}\n\nfn run() {}\n";
+
+    assert_eq!(
+      split(text, Segmentation::Paragraph),
+      vec!["fn main() {\n  run();\n}", "fn run() {}"]
+    );
+  }
+
+  #[test]
+  fn items_keep_indentation() {
+    let text = "fn main() {\n    let a = 1;\n\n    run(a);\n}\n\nfn run() {}\n";
+
+    assert_eq!(
+      split(text, Segmentation::Items),
+      vec!["fn main() {\n    let a = 1;\n\n    run(a);\n}", "fn run() {}"]
+    );
+    assert_eq!(
+      split(text, Segmentation::Paragraph),
+      vec!["fn main() {\n    let a = 1;", "    run(a);\n}", "fn run() {}"]
+    );
+  }
+
+  #[test]
+  fn sentences() {
+    let text = "Hello there! Is it \"you?\" Yes. 3.14 is pi\nNew line";
+
+    assert_eq!(
+      split(text, Segmentation::Sentence),
+      vec!["Hello there!", "Is it \"you?\"", "Yes.", "3.14 is pi", "New line"]
+    );
+  }
+
+  #[test]
+  fn length() {
+    let text = "One two. Three four. Five six. Seven.";
+
+    assert_eq!(
+      split(text, Segmentation::Length(15)),
+      vec!["One two. Three four.", "Five six. Seven."]
+    );
+  }
+}
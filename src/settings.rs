@@ -0,0 +1,127 @@
+use crate::normalize::Normalization;
+use crate::passages::Segmentation;
+use crate::text::InputMode;
+
+pub const SETTINGS_FILE: &str = ".category";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategorySettings {
+  pub passages: Segmentation,
+  pub normalization: Normalization,
+  pub input_mode: InputMode,
+}
+
+impl Default for CategorySettings {
+  fn default() -> CategorySettings {
+    CategorySettings {
+      passages: Segmentation::Whole,
+      normalization: Normalization::default(),
+      input_mode: InputMode::Prose,
+    }
+  }
+}
+
+impl CategorySettings {
+  // Settings files in parent categories apply to their subcategories unless overridden.
+  pub fn load(texts_dir: &str, category: &str) -> CategorySettings {
+    let mut settings = CategorySettings::default();
+    let mut path = std::path::PathBuf::from(texts_dir);
+    settings.apply_file(&path);
+    for component in std::path::Path::new(category).components() {
+      path.push(component);
+      settings.apply_file(&path);
+    }
+    settings.input_mode = match (settings.input_mode, settings.normalization.tab_width) {
+      (InputMode::Code { .. }, tab_width) => InputMode::Code {
+        tab_width: tab_width.unwrap_or(0),
+      },
+      (mode, _) => mode,
+    };
+    settings
+  }
+
+  fn apply_file(&mut self, category_path: &std::path::Path) {
+    if let Ok(fields) = std::fs::read_to_string(category_path.join(SETTINGS_FILE)) {
+      self.apply_fields(&fields);
+    }
+  }
+
+  fn apply_fields(&mut self, fields: &str) {
+    for (key, value) in crate::metadata::parse_fields(fields) {
+      let normalization = &mut self.normalization;
+      match &key[..] {
+        "passages" => {
+          if let Some(passages) = Segmentation::parse(&value) {
+            self.passages = passages;
+          }
+        }
+        "line_endings" => set_bool(&mut normalization.line_endings, &value),
+        "fold_quotes" => set_bool(&mut normalization.fold_quotes, &value),
+        "collapse_whitespace" => set_bool(&mut normalization.collapse_whitespace, &value),
+        "strip_diacritics" => set_bool(&mut normalization.strip_diacritics, &value),
+        "nfc" => set_bool(&mut normalization.nfc, &value),
+        "mode" => match &value.to_lowercase()[..] {
+          "code" => self.input_mode = InputMode::Code { tab_width: 0 },
+          "prose" => self.input_mode = InputMode::Prose,
+          _ => (),
+        },
+        "tab_width" => {
+          if let Ok(tab_width) = value.parse::<usize>() {
+            normalization.tab_width = if tab_width == 0 { None } else { Some(tab_width) };
+          }
+        }
+        _ => (),
+      }
+    }
+  }
+}
+
+fn set_bool(setting: &mut bool, value: &str) {
+  match &value.to_lowercase()[..] {
+    "yes" | "true" | "on" | "1" => *setting = true,
+    "no" | "false" | "off" | "0" => *setting = false,
+    _ => (),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn inherited() {
+    let dir = String::from("test_0d6e4a9b3"); // random
+    std::fs::create_dir_all(dir.clone() + "/cat1/sub1").unwrap();
+    std::fs::create_dir_all(dir.clone() + "/cat1/sub2").unwrap();
+    std::fs::write(dir.clone() + "/cat1/.category", "passages: sentence\n").unwrap();
+    std::fs::write(
+      dir.clone() + "/cat1/sub2/.category",
+      "passages: 200\nstrip_diacritics: yes\ntab_width: 0\nmode: code\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+      CategorySettings::load(&dir, "cat1/sub1").passages,
+      Segmentation::Sentence
+    );
+    assert_eq!(
+      CategorySettings::load(&dir, "cat1/sub2").passages,
+      Segmentation::Length(200)
+    );
+    assert_eq!(
+      CategorySettings::load(&dir, "cat1/sub2").normalization,
+      Normalization {
+        strip_diacritics: true,
+        tab_width: None,
+        ..Normalization::default()
+      }
+    );
+    assert_eq!(
+      CategorySettings::load(&dir, "cat1/sub2").input_mode,
+      InputMode::Code { tab_width: 0 }
+    );
+    assert_eq!(CategorySettings::load(&dir, "other"), CategorySettings::default());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
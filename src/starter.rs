@@ -0,0 +1,109 @@
+use crate::categories::Text;
+use crate::metadata::Metadata;
+use crate::text::InputMode;
+
+pub struct StarterText {
+  pub language: &'static str,
+  pub title: &'static str,
+  pub content: &'static str,
+}
+
+// Compiled into the binary so there is always something to type, even without a texts directory.
+pub const LIBRARY: [StarterText; 6] = [
+  StarterText {
+    language: "en",
+    title: "Welcome",
+    content: include_str!("starter/en-welcome"),
+  },
+  StarterText {
+    language: "en",
+    title: "Touch typing",
+    content: include_str!("starter/en-keyboard"),
+  },
+  StarterText {
+    language: "en",
+    title: "Pangrams",
+    content: include_str!("starter/en-pangrams"),
+  },
+  StarterText {
+    language: "bg",
+    title: "Добре дошли",
+    content: include_str!("starter/bg-welcome"),
+  },
+  StarterText {
+    language: "bg",
+    title: "Слепият метод",
+    content: include_str!("starter/bg-keyboard"),
+  },
+  StarterText {
+    language: "rust",
+    title: "FizzBuzz",
+    content: include_str!("starter/rust-fizzbuzz"),
+  },
+];
+
+const DEFAULT_LANGUAGE: &str = "en";
+
+// Guesses the language from a category component such as `Bulgarian` or `Rust`, defaulting to
+// English.
+pub fn language_for_category(category: &str) -> &'static str {
+  std::path::Path::new(category)
+    .components()
+    .rev()
+    .find_map(|component| {
+      match &component.as_os_str().to_string_lossy().to_lowercase()[..] {
+        "en" | "english" => Some("en"),
+        "bg" | "bulgarian" => Some("bg"),
+        "rs" | "rust" => Some("rust"),
+        _ => None,
+      }
+    })
+    .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+pub fn texts(language: &str) -> std::vec::Vec<&'static StarterText> {
+  LIBRARY
+    .iter()
+    .filter(|text| text.language == language)
+    .collect()
+}
+
+impl StarterText {
+  pub fn to_text(&self) -> Text {
+    let input_mode = match crate::highlight::detect("", Some(self.language)) {
+      Some(_) => InputMode::Code { tab_width: 4 },
+      None => InputMode::Prose,
+    };
+    Text {
+      metadata: Metadata {
+        title: Some(String::from(self.title)),
+        source: Some(String::from("typing-racer starter library")),
+        language: Some(String::from(self.language)),
+        ..Metadata::default()
+      },
+      input_mode,
+      ..Text::from(crate::normalize::Normalization::default().apply(self.content))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn library_not_empty() {
+    for text in &LIBRARY {
+      assert!(!text.content.trim().is_empty(), "{}", text.title);
+    }
+    assert!(!texts(DEFAULT_LANGUAGE).is_empty());
+  }
+
+  #[test]
+  fn category_language() {
+    assert_eq!(language_for_category("Bulgarian"), "bg");
+    assert_eq!(language_for_category("Rust/async"), "rust");
+    assert_eq!(language_for_category("Basic"), "en");
+    assert_eq!(texts("rust")[0].to_text().input_mode, InputMode::Code { tab_width: 4 });
+  }
+}
@@ -1,10 +1,74 @@
-const LOGFILE_PATH: &str = ".typeracer-log";
+use crate::categories::Text;
+use crate::clock::{Clock, SystemClock};
+use crate::compose;
+use crate::error::{Error, Result};
+use crate::history::{self, History};
+use crate::metadata::Metadata;
+use crate::passages::Passage;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputMode {
+  Prose,
+  // Enter also types the next line's indentation and Tab types up to `tab_width` spaces.
+  Code { tab_width: usize },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ErrorMode {
+  // Anything can be typed, right or wrong.
+  #[default]
+  Free,
+  // Wrong keystrokes count against accuracy but are not typed.
+  Stop,
+  // A word with errors in it has to be fixed before typing the space after it.
+  CorrectWord,
+}
+
+impl ErrorMode {
+  pub fn parse(value: &str) -> Option<ErrorMode> {
+    match &value.trim().to_lowercase()[..] {
+      "free" => Some(ErrorMode::Free),
+      "stop" => Some(ErrorMode::Stop),
+      "word" => Some(ErrorMode::CorrectWord),
+      _ => None,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      ErrorMode::Free => "free",
+      ErrorMode::Stop => "stop",
+      ErrorMode::CorrectWord => "word",
+    }
+  }
+}
 
-struct DataPoint {
-  cpm: f32,
-  _time: u64,
-  _accuracy: f32,
-  _wpm: f32,
+// A grapheme of the text lined up with what was typed for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aligned<'a> {
+  // Byte offset of `expected` in the text.
+  pub offset: usize,
+  pub expected: &'a str,
+  pub typed: Option<&'a str>,
+}
+
+impl Aligned<'_> {
+  pub fn is_wrong(&self) -> bool {
+    self
+      .typed
+      .is_some_and(|typed| !compose::equivalent(typed, self.expected))
+  }
+}
+
+// How a grapheme came to be typed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Input {
+  Key,
+  // Whitespace typed along with a keystroke, like the indentation after Enter in code.
+  Auto,
+  // Left out of a word by typing a space in the middle of it.
+  Skipped,
 }
 
 struct LetterInfo {
@@ -13,55 +77,507 @@ struct LetterInfo {
   errors: usize,
 }
 
+// How a letter has gone in a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LetterStats {
+  pub letter: char,
+  // Times it was typed right, and the average time that took.
+  pub count: usize,
+  pub average: std::time::Duration,
+  pub errors: usize,
+}
+
+// The stats of a run so far. Speeds and accuracy are `None` until something is typed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+  pub wpm: Option<f32>,
+  pub cpm: Option<f32>,
+  pub accuracy: Option<f32>,
+  pub skipped: u32,
+  // In the order of the letters.
+  pub letters: std::vec::Vec<LetterStats>,
+}
+
 pub struct TextManager {
+  text_name: String,
+  metadata: Metadata,
+  passage: Option<Passage>,
+  text_id: String,
   current_text: String,
-  typed_text: String,
-  start_time: Option<std::time::Instant>,
-  last_type: Option<std::time::Instant>,
+  // Byte offset of every grapheme of `current_text`, followed by its length. Positions in the text
+  // count graphemes, so that a letter with combining accents or an emoji is typed as one.
+  offsets: std::vec::Vec<usize>,
+  // Index of the grapheme at the start of every line of `current_text`.
+  line_starts: std::vec::Vec<usize>,
+  typed: std::vec::Vec<String>,
+  input_mode: InputMode,
+  error_mode: ErrorMode,
+  skip_words: bool,
+  inputs: std::vec::Vec<Input>,
+  correct: std::vec::Vec<bool>,
+  // A dead key waiting for the letter to put its accent on, with the combining mark it stands for.
+  pending: Option<(char, char)>,
+  // Positions where the typed text switches between right and wrong, starting with right.
+  flips: std::vec::Vec<usize>,
+  // What each finished word added to `typed_words`, so that deleting can take it back.
+  word_scores: std::vec::Vec<f32>,
+  clock: Box<dyn Clock>,
+  history: History,
+  start_time: Option<std::time::Duration>,
+  last_type: Option<std::time::Duration>,
+  // Keystrokes and correct keystrokes, not counting auto-typed whitespace. Skipped graphemes count
+  // as wrong keystrokes.
+  total_typed: u32,
   typed_chars: u32,
+  skipped: u32,
   typed_words: f32,
-  accuracy: f32,
-  log_file: Option<std::fs::File>,
   letters: std::collections::HashMap<char, LetterInfo>,
 }
 
 impl TextManager {
-  pub fn new(current_text: String) -> Self {
-    assert!(current_text.len() > 0);
-    TextManager {
-      current_text: String::from(current_text),
-      typed_text: String::new(),
+  pub fn new(text: impl Into<Text>) -> Result<Self> {
+    Self::with_clock(text, SystemClock::new())
+  }
+
+  pub fn with_clock(text: impl Into<Text>, clock: impl Clock + 'static) -> Result<Self> {
+    let text = text.into();
+    if text.content.is_empty() {
+      return Err(Error::EmptyText(text.name));
+    }
+    let mut offsets = vec![];
+    let mut line_starts = vec![0];
+    for (i, (offset, grapheme)) in text.content.grapheme_indices(true).enumerate() {
+      offsets.push(offset);
+      if grapheme.ends_with('\n') {
+        line_starts.push(i + 1);
+      }
+    }
+    offsets.push(text.content.len());
+    Ok(TextManager {
+      text_name: text.name,
+      metadata: text.metadata,
+      passage: text.passage,
+      text_id: history::text_id(&text.content),
+      current_text: text.content,
+      offsets,
+      line_starts,
+      typed: vec![],
+      input_mode: text.input_mode,
+      error_mode: ErrorMode::Free,
+      skip_words: false,
+      inputs: vec![],
+      correct: vec![],
+      pending: None,
+      flips: vec![],
+      word_scores: vec![],
+      clock: Box::new(clock),
+      history: History::default(),
       start_time: None,
       last_type: None,
+      total_typed: 0,
       typed_words: 0.,
       typed_chars: 0,
-      accuracy: 0.,
-      log_file: std::fs::OpenOptions::new()
-        .write(true)
-        .append(true)
-        .create(true)
-        .open(LOGFILE_PATH)
-        .ok(),
+      skipped: 0,
       letters: std::collections::HashMap::new(),
+    })
+  }
+
+  pub fn get_metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  pub fn get_text_name(&self) -> &str {
+    &self.text_name
+  }
+
+  pub fn get_text_id(&self) -> &str {
+    &self.text_id
+  }
+
+  pub fn get_text(&self) -> &str {
+    &self.current_text
+  }
+
+  pub fn get_passage(&self) -> Option<Passage> {
+    self.passage
+  }
+
+  pub fn to_text(&self) -> Text {
+    Text {
+      name: self.text_name.clone(),
+      content: self.current_text.clone(),
+      metadata: self.metadata.clone(),
+      passage: self.passage,
+      input_mode: self.input_mode,
     }
   }
 
+  pub fn get_input_mode(&self) -> InputMode {
+    self.input_mode
+  }
+
+  pub fn get_error_mode(&self) -> ErrorMode {
+    self.error_mode
+  }
+
+  pub fn set_error_mode(&mut self, error_mode: ErrorMode) {
+    self.error_mode = error_mode;
+  }
+
+  // Whether a space typed in the middle of a word skips the rest of it. Words with errors still
+  // have to be fixed when the error mode is `CorrectWord`.
+  pub fn set_skip_words(&mut self, skip_words: bool) {
+    self.skip_words = skip_words;
+  }
+
+  // Where `end_run` logs the run and `get_improvement` reads runs from.
+  pub fn set_history(&mut self, history: History) {
+    self.history = history;
+  }
+
+  // Graphemes left out by skipping words.
+  pub fn get_skipped(&self) -> u32 {
+    self.skipped
+  }
+
   pub fn type_char(&mut self, c: char) {
     if self.start_time.is_none() {
-      self.start_time = Some(std::time::Instant::now());
-      self.last_type = Some(std::time::Instant::now());
+      self.start_time = Some(self.clock.now());
+      self.last_type = self.start_time;
+    }
+
+    if let Some((accent, mark)) = self.pending.take() {
+      let composed = compose::compose(c, mark);
+      if c != ' ' && composed.graphemes(true).count() == 1 {
+        self.insert(composed, Input::Key);
+        return;
+      }
+      // The way dead keys type their accent on its own is by following them with a space.
+      self.insert_char(accent, Input::Key);
+      if c == ' ' {
+        return;
+      }
+    }
+
+    match (self.input_mode, c) {
+      (InputMode::Code { tab_width }, '\t') if tab_width > 0 => {
+        let column = self.get_column();
+        let spaces = self
+          .get_remaining_text()
+          .chars()
+          .take(tab_width - column % tab_width)
+          .take_while(|c| *c == ' ')
+          .count();
+        if spaces == 0 {
+          self.insert_char(c, Input::Key);
+        }
+        for _ in 0..spaces {
+          self.insert_char(' ', Input::Auto);
+        }
+      }
+      (InputMode::Code { .. }, '\n') => {
+        let expected = self.get_remaining_text().chars().next();
+        if self.insert_char(c, Input::Key) && expected == Some('\n') {
+          let indentation: std::vec::Vec<char> = self
+            .get_remaining_text()
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+          for c in indentation {
+            self.insert_char(c, Input::Auto);
+          }
+        }
+      }
+      (_, ' ') if self.skips_word() => self.skip_word(),
+      _ => self.type_composed(c),
     }
+  }
+
+  // Whether a space typed now skips the rest of the word.
+  fn skips_word(&self) -> bool {
+    let position = self.typed.len();
+    let in_word = |position| self.grapheme_at(position).is_some_and(|g| !Self::is_space(g));
+    self.skip_words
+      && self.error_mode != ErrorMode::CorrectWord
+      && position > 0
+      && in_word(position - 1)
+      && in_word(position)
+  }
+
+  fn skip_word(&mut self) {
+    while self
+      .grapheme_at(self.typed.len())
+      .is_some_and(|grapheme| !Self::is_space(grapheme))
+    {
+      self.insert(String::new(), Input::Skipped);
+    }
+    self.insert_char(' ', Input::Key);
+  }
 
-    if self.typed_text.len() < self.current_text.len() {
-      self.typed_text.push(c);
-      self.update_stats(true, c);
+  fn type_composed(&mut self, c: char) {
+    // A combining char completes the grapheme typed before it, which is then judged again.
+    let extended = match (self.typed.last(), self.inputs.last()) {
+      (Some(last), Some(Input::Key)) => Some(format!("{}{}", last, c)),
+      _ => None,
+    }
+    .filter(|grapheme| grapheme.graphemes(true).count() == 1);
+    let previous_correct = self.correct.last() != Some(&false);
+    let expected = self.grapheme_at(self.typed.len()).unwrap_or_default();
+    let dead_key = compose::dead_key_mark(c)
+      .filter(|mark| c.to_string() != expected && compose::has_mark(expected, *mark));
+
+    match (extended, dead_key) {
+      (Some(grapheme), _) if !previous_correct || dead_key.is_none() => {
+        let last = self.typed.last().cloned().unwrap_or_default();
+        self.retract();
+        // What was typed stays as it was if the error mode keeps out the completed grapheme.
+        if !self.insert(grapheme, Input::Key) {
+          self.insert(last, Input::Key);
+        }
+      }
+      (_, Some(mark)) => self.pending = Some((c, mark)),
+      _ => {
+        self.insert_char(c, Input::Key);
+      }
     }
   }
 
   pub fn del_char(&mut self) {
-    if let Some(c) = self.typed_text.pop() {
-      self.update_stats(false, c);
+    if self.pending.take().is_some() {
+      return;
+    }
+    // Auto-typed whitespace and skipped words are removed as a block, as if they were a single
+    // keystroke.
+    match self.inputs.last().copied() {
+      Some(input @ Input::Auto) | Some(input @ Input::Skipped) => {
+        while self.inputs.last() == Some(&input) {
+          self.remove_char();
+        }
+      }
+      _ => self.remove_char(),
+    }
+  }
+
+  // Deletes back to the start of the word before the cursor, along with the spaces after it.
+  pub fn del_word(&mut self) {
+    if self.pending.take().is_some() {
+      return;
+    }
+    let space_before = |manager: &Self| {
+      let position = manager.typed.len().checked_sub(1)?;
+      manager.grapheme_at(position).map(Self::is_space)
     };
+    while space_before(self) == Some(true) {
+      self.del_char();
+    }
+    while space_before(self) == Some(false) {
+      self.del_char();
+    }
+  }
+
+  fn insert_char(&mut self, c: char, input: Input) -> bool {
+    self.insert(String::from(c), input)
+  }
+
+  // Returns whether the grapheme was typed, rather than kept out by the error mode.
+  fn insert(&mut self, grapheme: String, input: Input) -> bool {
+    self.abandon_partial();
+    let position = self.typed.len();
+    let (correct, letter, rejected) = match self.grapheme_at(position) {
+      Some(expected) => {
+        let correct = input != Input::Skipped && compose::equivalent(&grapheme, expected);
+        let rejected = input == Input::Key && self.rejects(position, &grapheme, expected, correct);
+        (correct, Self::letter(expected), rejected)
+      }
+      None => return false,
+    };
+    if rejected {
+      self.total_typed += 1;
+      self.record_letter(letter, false);
+      return false;
+    }
+    let previous = position == 0 || self.correct[position - 1];
+    if correct != previous {
+      self.flips.push(position);
+    }
+    self.typed.push(grapheme);
+    self.inputs.push(input);
+    self.correct.push(correct);
+
+    if input != Input::Auto {
+      self.total_typed += 1;
+      if correct {
+        self.typed_chars += 1;
+      }
+    }
+    if self.is_word_end(position) {
+      let score = self.typed_chars as f32 / self.total_typed.max(1) as f32;
+      self.word_scores.push(score);
+      self.typed_words += score;
+    }
+    match input {
+      Input::Key => self.record_letter(letter, correct),
+      // Not a keystroke, so it takes no time, but the letter was missed.
+      Input::Skipped => {
+        self.skipped += 1;
+        self.letter_info(letter).errors += 1;
+      }
+      Input::Auto => (),
+    }
+    true
+  }
+
+  fn letter_info(&mut self, letter: char) -> &mut LetterInfo {
+    self.letters.entry(letter).or_insert(LetterInfo {
+      duration: std::time::Duration::from_secs(0),
+      count: 0,
+      errors: 0,
+    })
+  }
+
+  fn record_letter(&mut self, letter: char, correct: bool) {
+    let now = self.clock.now();
+    let last_type = self.last_type;
+    let info = self.letter_info(letter);
+    if correct {
+      info.count += 1;
+      if let Some(last_type) = last_type {
+        info.duration += now.saturating_sub(last_type);
+      }
+    } else {
+      info.errors += 1;
+    }
+    self.last_type = Some(now);
+  }
+
+  fn remove_char(&mut self) {
+    let (input, correct) = match (self.typed.pop(), self.inputs.pop(), self.correct.pop()) {
+      (Some(_), Some(input), Some(correct)) => (input, correct),
+      _ => return,
+    };
+    let position = self.typed.len();
+    if self.flips.last() == Some(&position) {
+      self.flips.pop();
+    }
+    if self.is_word_end(position) {
+      self.typed_words -= self.word_scores.pop().unwrap_or_default();
+      if self.word_scores.is_empty() {
+        self.typed_words = 0.;
+      }
+    }
+    match input {
+      Input::Auto => return,
+      // Unlike a wrong keystroke, a skipped grapheme was never typed, so its error goes too.
+      Input::Skipped => {
+        self.skipped -= 1;
+        if let Some(info) = self.letters.get_mut(&self.letter_at(position)) {
+          info.errors = info.errors.saturating_sub(1);
+        }
+      }
+      Input::Key => (),
+    }
+
+    self.total_typed -= 1;
+    if correct {
+      self.typed_chars -= 1;
+      if let Some(info) = self.letters.get_mut(&self.letter_at(position)) {
+        info.count = info.count.saturating_sub(1);
+      }
+    }
+  }
+
+  // Whether the error mode keeps `grapheme` from being typed where `expected` is.
+  fn rejects(&self, position: usize, grapheme: &str, expected: &str, correct: bool) -> bool {
+    match self.error_mode {
+      ErrorMode::Free => false,
+      // The start of a grapheme that more combining chars will complete is not an error yet.
+      ErrorMode::Stop => !correct && !compose::is_start_of(grapheme, expected),
+      ErrorMode::CorrectWord => {
+        let word_start = (0..position)
+          .rev()
+          .find(|position| self.grapheme_at(*position).is_some_and(Self::is_space))
+          .map_or(0, |position| position + 1);
+        Self::is_space(expected) && self.correct[word_start..position].contains(&false)
+      }
+    }
+  }
+
+  // In stop mode, a wrong grapheme can only be the start of one still being composed. Once
+  // something else is typed it won't be completed, so it is taken back as a rejected keystroke.
+  fn abandon_partial(&mut self) {
+    if self.error_mode == ErrorMode::Stop && self.correct.last() == Some(&false) {
+      self.remove_char();
+      self.total_typed += 1;
+    }
+  }
+
+  // Unlike deleting, takes back the error of a wrong grapheme as well.
+  fn retract(&mut self) {
+    let position = self.typed.len() - 1;
+    if !self.correct[position] {
+      if let Some(info) = self.letters.get_mut(&self.letter_at(position)) {
+        info.errors = info.errors.saturating_sub(1);
+      }
+    }
+    self.remove_char();
+  }
+
+  fn grapheme_at(&self, position: usize) -> Option<&str> {
+    let end = *self.offsets.get(position + 1)?;
+    Some(&self.current_text[self.offsets[position]..end])
+  }
+
+  // Letter stats are kept by the first char of a grapheme, which is the whole grapheme for
+  // anything but combining sequences and emoji.
+  fn letter(grapheme: &str) -> char {
+    grapheme.chars().next().unwrap_or_default()
+  }
+
+  fn letter_at(&self, position: usize) -> char {
+    self.grapheme_at(position).map(Self::letter).unwrap_or_default()
+  }
+
+  fn is_space(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+  }
+
+  // Words are scored when the text moves past them, at the first grapheme after the word.
+  fn is_word_end(&self, position: usize) -> bool {
+    let is_word_char = |position| self.letter_at(position).is_alphanumeric();
+    position > 0 && is_word_char(position - 1) && !is_word_char(position)
+  }
+
+  fn get_remaining_text(&self) -> &str {
+    &self.current_text[self.offsets[self.typed.len()]..]
+  }
+
+  fn get_line_start(&self, position: usize) -> usize {
+    let line = self.line_starts.partition_point(|start| *start <= position) - 1;
+    self.line_starts[line]
+  }
+
+  fn get_column(&self) -> usize {
+    let position = self.typed.len();
+    position - self.get_line_start(position)
+  }
+
+  // The graphemes of the text in the byte range, which should start at one, with what was typed
+  // for each of them so far.
+  pub fn get_aligned(&self, range: std::ops::Range<usize>) -> std::vec::Vec<Aligned<'_>> {
+    let start = self.offsets.partition_point(|offset| *offset < range.start);
+    let end = self.offsets.partition_point(|offset| *offset < range.end);
+    (start..end.min(self.offsets.len() - 1))
+      .map(|position| Aligned {
+        offset: self.offsets[position],
+        expected: &self.current_text[self.offsets[position]..self.offsets[position + 1]],
+        typed: self.typed.get(position).map(String::as_str),
+      })
+      .collect()
+  }
+
+  // Byte offset of the next grapheme to type.
+  pub fn get_cursor(&self) -> usize {
+    self.offsets[self.typed.len()]
   }
 
   pub fn get_slowest_letters(&self) -> std::vec::Vec<(char, u128)> {
@@ -70,7 +586,7 @@ impl TextManager {
       .iter()
       .map(|(c, info)| {
         (
-          c.clone(),
+          *c,
           if info.count == 0 {
             0
           } else {
@@ -79,71 +595,117 @@ impl TextManager {
         )
       })
       .collect();
-    vec.sort_by_key(|(_c, v)| std::cmp::Reverse(v.clone()));
-    return vec;
+    vec.sort_by_key(|(_c, v)| std::cmp::Reverse(*v));
+    vec
   }
 
   pub fn get_most_error_letters(&self) -> std::vec::Vec<(char, usize)> {
     let mut vec: std::vec::Vec<(char, usize)> = self
       .letters
       .iter()
-      .map(|(c, info)| (c.clone(), info.errors))
+      .map(|(c, info)| (*c, info.errors))
       .collect();
-    vec.sort_by_key(|(_c, v)| std::cmp::Reverse(v.clone()));
-    return vec;
+    vec.sort_by_key(|(_c, v)| std::cmp::Reverse(*v));
+    vec
   }
   
   pub fn get_cpm(&self) -> Option<f32> {
-    if let Some(start_time) = self.start_time {
-      let mins = start_time.elapsed().as_millis() as f32 / 1000. / 60.;
-      Some(self.typed_chars as f32 / mins)
-    } else {
-      None
-    }
+    self.elapsed_mins().map(|mins| self.typed_chars as f32 / mins)
   }
 
   pub fn get_wpm(&self) -> Option<f32> {
-    if let Some(start_time) = self.start_time {
-      let mins = start_time.elapsed().as_millis() as f32 / 1000. / 60.;
-      Some(self.typed_words as f32 / mins)
-    } else {
-      None
-    }
+    self.elapsed_mins().map(|mins| self.typed_words / mins)
+  }
+
+  // None until some time has passed, as there is no speed to tell before that.
+  fn elapsed_mins(&self) -> Option<f32> {
+    let start_time = self.start_time?;
+    let mins = self.clock.now().saturating_sub(start_time).as_millis() as f32 / 1000. / 60.;
+    Some(mins).filter(|mins| *mins > 0.)
   }
 
   pub fn get_accuracy(&self) -> Option<f32> {
-    if self.accuracy.is_nan() {
+    if self.total_typed == 0 {
       None
     } else {
-      Some(self.accuracy)
+      Some(self.typed_chars as f32 / self.total_typed as f32)
     }
   }
 
-  pub fn end_run(&mut self) -> Option<()> {
+  pub fn get_summary(&self) -> Summary {
+    let mut letters: std::vec::Vec<LetterStats> = self
+      .letters
+      .iter()
+      .map(|(letter, info)| LetterStats {
+        letter: *letter,
+        count: info.count,
+        average: info.duration.checked_div(info.count as u32).unwrap_or_default(),
+        errors: info.errors,
+      })
+      .collect();
+    letters.sort_by_key(|stats| stats.letter);
+    Summary {
+      wpm: self.get_wpm(),
+      cpm: self.get_cpm(),
+      accuracy: self.get_accuracy(),
+      skipped: self.skipped,
+      letters,
+    }
+  }
+
+  // Logs the run, and returns whether there was anything typed to log.
+  pub fn end_run(&mut self) -> Result<bool> {
     let now = std::time::SystemTime::now()
       .duration_since(std::time::SystemTime::UNIX_EPOCH)
       .unwrap_or_default()
       .as_secs();
-    let acc = self.get_accuracy();
-    let wpm = self.get_wpm();
-    let cpm = self.get_cpm();
-
-    if let Some(log_file) = &mut self.log_file {
-      use std::io::Write;
-      if let (Some(acc), Some(wpm), Some(cpm)) = (acc, wpm, cpm) {
-        log_file
-          .write_all(format!("{:?} {:?} {:?} {:?}\n", now, acc, wpm, cpm).as_bytes())
-          .ok()
-      } else {
-        None
+
+    if let (Some(accuracy), Some(wpm), Some(cpm)) =
+      (self.get_accuracy(), self.get_wpm(), self.get_cpm())
+    {
+      let mut fields = vec![];
+      if !self.text_name.is_empty() {
+        fields.push((String::from("text"), self.text_name.clone()));
       }
+      fields.push((String::from("id"), self.text_id.clone()));
+      if let Some(passage) = self.passage {
+        fields.push((String::from("passage"), passage.index.to_string()));
+      }
+      fields.push((String::from("errors"), String::from(self.error_mode.name())));
+      let slow: String = self
+        .get_slowest_letters()
+        .iter()
+        .filter(|(c, _)| c.is_alphabetic())
+        .flat_map(|(c, _)| c.to_lowercase())
+        .take(history::SLOW_LETTERS)
+        .collect();
+      if !slow.is_empty() {
+        fields.push((String::from("slow"), slow));
+      }
+      if self.skipped > 0 {
+        fields.push((String::from("skipped"), self.skipped.to_string()));
+      }
+      fields.extend(self.metadata.to_fields());
+      self.history.append(&history::Record {
+        time: now,
+        accuracy,
+        wpm,
+        cpm,
+        fields,
+      })?;
+      Ok(true)
     } else {
-      None
+      Ok(false)
     }
   }
 
-  pub fn get_improvement(&self, scale_x: usize, scale_y: usize) -> Option<std::vec::Vec<usize>> {
-    if let Some(raw_data) = self.get_raw_improvement() {
+  pub fn get_improvement(
+    &self,
+    scale_x: usize,
+    scale_y: usize,
+  ) -> Result<Option<std::vec::Vec<usize>>> {
+    let raw_data = self.history.load()?;
+    if !raw_data.is_empty() {
       let mut result = vec![];
 
       let mut max = 0.;
@@ -181,10 +743,7 @@ impl TextManager {
             idx2 = data_len - 1;
           }
 
-          let mut sum = 0.;
-          for i in idx1..idx2 + 1 {
-            sum += raw_data[i].cpm;
-          }
+          let sum: f32 = raw_data[idx1..idx2 + 1].iter().map(|point| point.cpm).sum();
 
           let cpm = sum / (idx2 + 1 - idx1) as f32;
 
@@ -192,169 +751,247 @@ impl TextManager {
         }
       }
 
-      Some(result)
+      Ok(Some(result))
     } else {
-      None
+      Ok(None)
     }
   }
 
+  // Alternating runs of right and wrong typed text, starting with a right one, followed by the
+  // untyped rest.
   pub fn get_text_parts(&self) -> std::vec::Vec<&str> {
-    let mut result = vec![];
-
-    let mut current_right = true;
-    let mut start = 0;
-    let mut text_iter = self.current_text.char_indices();
-    let mut last_idx = -1;
-    for (_, c) in self.typed_text.char_indices() {
-      let next = text_iter.next();
-      if let Some((text_i, text_char)) = next {
-        last_idx = text_i as i32;
-        if current_right && c != text_char {
-          let bound = Self::get_next_boundary(&self.current_text, text_i);
-          result.push(&self.current_text[start..bound]);
-          start = bound;
-          current_right = false;
-        }
+    self.get_text_parts_from(0)
+  }
 
-        if !current_right && c == text_char {
-          let bound = Self::get_next_boundary(&self.current_text, text_i);
-          result.push(&self.current_text[start..bound]);
-          start = bound;
-          current_right = true;
-        }
-      }
+  // Like `get_text_parts`, for the text from byte offset `start` on, which should be in the typed
+  // part. The first part is empty if the text there is wrong.
+  pub fn get_text_parts_from(&self, start: usize) -> std::vec::Vec<&str> {
+    let flips = &self.flips[self.flips.partition_point(|flip| self.offsets[*flip] <= start)..];
+    let mut result = vec![];
+    if (self.flips.len() - flips.len()) % 2 == 1 {
+      result.push("");
     }
-    let end = Self::get_next_boundary(&self.current_text, (last_idx + 1) as usize);
+    let mut start = start;
+    for flip in flips {
+      let bound = self.offsets[*flip];
+      result.push(&self.current_text[start..bound]);
+      start = bound;
+    }
+    let end = self.offsets[self.typed.len()].max(start);
     result.push(&self.current_text[start..end]);
     result.push(&self.current_text[end..]);
     result
   }
+}
 
-  fn update_stats(&mut self, has_inserted: bool, last_typed: char) {
-    self.typed_chars = 0;
-    self.typed_words = 0.;
-    let mut in_word = false;
-    let mut text_iter = self.current_text.chars();
-    let mut curr_word_chars = 0;
-    let mut curr_word_correct = 0;
-    let mut total_correct = 0;
-    let mut last_typed_real = '\0';
-    for typed in self.typed_text.chars() {
-      let text_next = text_iter.next();
-      if let Some(text_char) = text_next {
-        curr_word_chars += 1;
-        if typed == text_char {
-          self.typed_chars += 1;
-          curr_word_correct += 1;
-          total_correct += 1;
-        }
-        if in_word && !text_char.is_alphanumeric() {
-          self.typed_words += curr_word_correct as f32 / curr_word_chars as f32;
-          in_word = false;
-        }
-        if !in_word && text_char.is_alphanumeric() {
-          in_word = true;
-        }
-        last_typed_real = text_char;
-      }
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::clock::MockClock;
+  use std::time::Duration;
 
-    if has_inserted {
-      assert_ne!(last_typed_real, '\0');
-      let info = self.letters.entry(last_typed_real).or_insert(LetterInfo {
-        duration: std::time::Duration::from_secs(0),
-        count: 0,
-        errors: 0,
-      });
-      if last_typed == last_typed_real {
-        info.count += 1;
-        info.duration += std::time::Instant::now().duration_since(self.last_type.unwrap());
-      } else {
-        info.errors += 1;
-      }
-      self.last_type = Some(std::time::Instant::now());
-    } else {
-      last_typed_real = text_iter.next().unwrap();
+  #[test]
+  fn basic_parts() {
+    let mut t = TextManager::new(String::from("Hello, world!")).unwrap();
 
-      if last_typed == last_typed_real {
-        let info = self.letters.get_mut(&last_typed_real).unwrap();
-        info.count -= 1;
-      }
+    t.type_char('H');
+    t.type_char('e');
+    t.type_char('l');
+
+    t.type_char('x');
+
+    t.type_char('o');
+
+    assert_eq!(t.get_text_parts(), vec!["Hel", "l", "o", ", world!"]);
+  }
+
+  #[test]
+  fn graphemes() {
+    let mut t = TextManager::new(String::from("e\u{301}t 中😀")).unwrap();
+
+    // A combining accent typed after its letter makes it right.
+    t.type_char('e');
+    assert_eq!(t.get_text_parts(), vec!["", "e\u{301}", "t 中😀"]);
+    t.type_char('\u{301}');
+    assert_eq!(t.get_text_parts(), vec!["e\u{301}", "t 中😀"]);
+    assert!(t.get_most_error_letters().iter().all(|(_, errors)| *errors == 0));
+
+    for c in "t 中😀".chars() {
+      t.type_char(c);
     }
+    assert_eq!(t.get_text_parts(), vec!["e\u{301}t 中😀", ""]);
+    assert!((t.get_accuracy().unwrap() - 1.).abs() < 0.0001);
 
-    self.accuracy = total_correct as f32 / self.typed_text.len() as f32;
+    t.del_char();
+    t.del_char();
+    assert_eq!(t.get_text_parts(), vec!["e\u{301}t ", "中😀"]);
+    assert_eq!(t.get_cursor(), "e\u{301}t ".len());
   }
 
-  fn get_raw_improvement(&self) -> Option<std::vec::Vec<DataPoint>> {
-    let mut result = vec![];
+  #[test]
+  fn nfc_and_nfd() {
+    let mut t = TextManager::new(String::from("café e\u{301}")).unwrap();
 
-    let log_file = std::fs::File::open(LOGFILE_PATH);
-    if let Ok(log_file) = log_file {
-      let reader = std::io::BufReader::new(log_file);
-      use std::io::BufRead;
-      for line in reader.lines() {
-        if let Ok(line) = line {
-          let vec: std::vec::Vec<&str> = line.split(' ').collect();
-
-          if let (Some(time), Some(acc), Some(wpm), Some(cpm)) =
-            (vec.get(0), vec.get(1), vec.get(2), vec.get(3))
-          {
-            if let (Ok(time), Ok(acc), Ok(wpm), Ok(cpm)) =
-              (time.parse(), acc.parse(), wpm.parse(), cpm.parse())
-            {
-              result.push(DataPoint {
-                cpm: cpm,
-                _time: time,
-                _accuracy: acc,
-                _wpm: wpm,
-              });
-            } else {
-              return None;
-            }
-          } else {
-            return None;
-          }
-        } else {
-          return None;
-        }
-      }
-      Some(result)
-    } else {
-      None
+    for c in "cafe\u{301} é".chars() {
+      t.type_char(c);
     }
+    assert_eq!(t.get_text_parts(), vec!["café e\u{301}", ""]);
+    assert!((t.get_accuracy().unwrap() - 1.).abs() < 0.0001);
   }
 
-  fn get_next_boundary(text: &str, i: usize) -> usize {
-    let mut end = i;
-    while !text.is_char_boundary(end) {
-      end += 1;
+  #[test]
+  fn dead_keys() {
+    let mut t = TextManager::new(String::from("tête à ^")).unwrap();
+
+    // A spacing accent, or a combining one, typed before the letter.
+    for c in "t^ete \u{300}a ^".chars() {
+      t.type_char(c);
     }
-    return end;
+    assert_eq!(t.get_text_parts(), vec!["tête à ^", ""]);
+    assert!((t.get_accuracy().unwrap() - 1.).abs() < 0.0001);
+
+    let mut t = TextManager::new(String::from("ê")).unwrap();
+    t.type_char('^');
+    t.del_char();
+    assert_eq!(t.get_text_parts(), vec!["", "ê"]);
+    t.type_char('^');
+    t.type_char(' ');
+    assert_eq!(t.get_text_parts(), vec!["", "ê", ""]);
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+  #[test]
+  fn aligned() {
+    let mut t = TextManager::new(String::from("Здравей")).unwrap();
+
+    for c in "Здрь".chars() {
+      t.type_char(c);
+    }
+
+    let aligned = t.get_aligned("Зд".len().."Здрав".len());
+    assert_eq!(aligned.len(), 3);
+    assert_eq!(aligned[0].offset, "Зд".len());
+    assert_eq!((aligned[0].expected, aligned[0].typed), ("р", Some("р")));
+    assert_eq!((aligned[1].expected, aligned[1].typed), ("а", Some("ь")));
+    assert_eq!((aligned[2].expected, aligned[2].typed), ("в", None));
+    assert!(!aligned[0].is_wrong() && aligned[1].is_wrong() && !aligned[2].is_wrong());
+  }
 
   #[test]
-  fn basic_parts() {
-    let mut t = TextManager::new(String::from("Hello, world!"));
+  fn stop_on_error() {
+    let mut t = TextManager::new(String::from("ab é")).unwrap();
+    t.set_error_mode(ErrorMode::Stop);
+
+    for c in "axb e\u{301}".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_text_parts(), vec!["ab é", ""]);
+    assert!((t.get_accuracy().unwrap() - 0.8).abs() < 0.0001);
+    assert_eq!(t.get_most_error_letters()[0], ('b', 1));
+  }
+
+  #[test]
+  fn stop_on_unfinished_grapheme() {
+    let mut t = TextManager::new(String::from("é b")).unwrap();
+    t.set_error_mode(ErrorMode::Stop);
 
-    t.type_char('H');
     t.type_char('e');
-    t.type_char('l');
+    assert_eq!(t.get_text_parts(), vec!["", "é", " b"]);
+    for c in " b".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_text_parts(), vec!["", "é b"]);
+    assert_eq!(t.get_accuracy(), Some(0.));
+    assert_eq!(t.get_most_error_letters()[0], ('é', 3));
 
-    t.type_char('x');
+    for c in "e\u{301} b".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_text_parts(), vec!["é b", ""]);
+  }
 
-    t.type_char('o');
+  #[test]
+  fn correct_word() {
+    let mut t = TextManager::new(String::from("ab cd")).unwrap();
+    t.set_error_mode(ErrorMode::CorrectWord);
 
-    assert_eq!(t.get_text_parts(), vec!["Hel", "l", "o", ", world!"]);
+    for c in "ax c".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_text_parts(), vec!["a", "b", " cd"]);
+
+    t.del_char();
+    for c in "b c".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_text_parts(), vec!["ab c", "d"]);
+  }
+
+  #[test]
+  fn del_word() {
+    let mut t = TextManager::new(String::from("one two three")).unwrap();
+
+    for c in "one tx".chars() {
+      t.type_char(c);
+    }
+    t.del_word();
+    assert_eq!(t.get_cursor(), 4);
+    assert_eq!(t.get_accuracy(), Some(1.));
+    // Like deleting a char, deleting the word keeps its errors.
+    assert_eq!(t.get_most_error_letters()[0], ('w', 1));
+
+    t.del_word();
+    assert_eq!(t.get_cursor(), 0);
+    t.del_word();
+    assert_eq!(t.get_cursor(), 0);
+  }
+
+  #[test]
+  fn skip_words() {
+    let mut t = TextManager::new(String::from("abc def")).unwrap();
+    t.set_skip_words(true);
+
+    for c in "ax ".chars() {
+      t.type_char(c);
+    }
+    assert_eq!(t.get_cursor(), 4);
+    assert_eq!(t.get_skipped(), 1);
+    assert_eq!(t.get_text_parts(), vec!["a", "bc", " ", "def"]);
+    assert_eq!(t.get_accuracy(), Some(0.5));
+    let mut errors = t.get_most_error_letters();
+    errors.retain(|(_, count)| *count > 0);
+    errors.sort();
+    assert_eq!(errors, vec![('b', 1), ('c', 1)]);
+
+    // A space at the start of a word is just a wrong keystroke.
+    t.type_char(' ');
+    assert_eq!(t.get_cursor(), 5);
+    assert_eq!(t.get_skipped(), 1);
+
+    t.del_char();
+    t.del_char();
+    t.del_char();
+    assert_eq!(t.get_cursor(), 2);
+    assert_eq!(t.get_skipped(), 0);
+    let errors = t.get_most_error_letters();
+    assert!(errors.contains(&('b', 1)));
+    assert!(errors.contains(&('c', 0)));
+  }
+
+  #[test]
+  fn parts_from() {
+    let mut t = TextManager::new(String::from("Hello, world!")).unwrap();
+
+    for c in "Helxo".chars() {
+      t.type_char(c);
+    }
+
+    assert_eq!(t.get_text_parts_from(3), vec!["", "l", "o", ", world!"]);
+    assert_eq!(t.get_text_parts_from(4), vec!["o", ", world!"]);
   }
 
   #[test]
   fn basic_del() {
-    let mut t = TextManager::new(String::from("Hello, world!"));
+    let mut t = TextManager::new(String::from("Hello, world!")).unwrap();
 
     t.type_char('H');
     t.type_char('e');
@@ -372,7 +1009,7 @@ mod tests {
 
   #[test]
   fn unicode_parts() {
-    let mut t = TextManager::new(String::from("Здравей, свят!"));
+    let mut t = TextManager::new(String::from("Здравей, свят!")).unwrap();
 
     t.type_char('З');
     t.type_char('д');
@@ -387,7 +1024,7 @@ mod tests {
 
   #[test]
   fn unicode_parts_mixed() {
-    let mut t = TextManager::new(String::from("Здравей, свят!"));
+    let mut t = TextManager::new(String::from("Здравей, свят!")).unwrap();
 
     t.type_char('З');
 
@@ -403,7 +1040,7 @@ mod tests {
 
   #[test]
   fn unicode_parts_mixed_reverse() {
-    let mut t = TextManager::new(String::from("Hello, world!"));
+    let mut t = TextManager::new(String::from("Hello, world!")).unwrap();
 
     t.type_char('H');
 
@@ -420,14 +1057,14 @@ mod tests {
 
   #[test]
   fn parts_empty() {
-    let t = TextManager::new(String::from("Hello"));
+    let t = TextManager::new(String::from("Hello")).unwrap();
 
     assert_eq!(t.get_text_parts(), vec!["", "Hello"]);
   }
 
   #[test]
   fn error_letters() {
-    let mut t = TextManager::new(String::from("Hello world!"));
+    let mut t = TextManager::new(String::from("Hello world!")).unwrap();
 
     t.type_char('H');
     t.type_char('x');
@@ -443,20 +1080,21 @@ mod tests {
 
   #[test]
   fn slowest_letters() {
-    let mut t = TextManager::new(String::from("Hello world!"));
+    let clock = MockClock::new();
+    let mut t = TextManager::with_clock(String::from("Hello world!"), clock.clone()).unwrap();
 
     t.type_char('H');
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    clock.advance(Duration::from_millis(100));
     t.type_char('e');
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    clock.advance(Duration::from_millis(100));
     t.type_char('l');
-    std::thread::sleep(std::time::Duration::from_millis(300));
+    clock.advance(Duration::from_millis(300));
     t.type_char('l');
-    std::thread::sleep(std::time::Duration::from_millis(250));
+    clock.advance(Duration::from_millis(250));
     t.type_char('o');
     t.type_char(' ');
     t.type_char('w');
-    std::thread::sleep(std::time::Duration::from_millis(250));
+    clock.advance(Duration::from_millis(250));
     t.type_char('o');
 
     let letters = t.get_slowest_letters();
@@ -468,11 +1106,12 @@ mod tests {
 
   #[test]
   fn slowest_letters_retype() {
-    let mut t = TextManager::new(String::from("Hello"));
+    let clock = MockClock::new();
+    let mut t = TextManager::with_clock(String::from("Hello"), clock.clone()).unwrap();
 
     t.type_char('H');
 
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    clock.advance(Duration::from_millis(100));
     t.type_char('e');
     t.del_char();
     t.type_char('e');
@@ -483,7 +1122,7 @@ mod tests {
     t.del_char();
     t.type_char('e');
 
-    std::thread::sleep(std::time::Duration::from_millis(50));
+    clock.advance(Duration::from_millis(50));
     t.type_char('l');
 
     let letters = t.get_slowest_letters();
@@ -495,7 +1134,7 @@ mod tests {
 
   #[test]
   fn accuracy() {
-    let mut t = TextManager::new(String::from("Hello"));
+    let mut t = TextManager::new(String::from("Hello")).unwrap();
 
     t.type_char('H');
     t.type_char('x');
@@ -508,4 +1147,172 @@ mod tests {
     let acc = t.get_accuracy().unwrap();
     assert!((acc - 0.6).abs() < 0.0001);
   }
+
+  #[test]
+  fn code_indentation() {
+    let mut t = TextManager::new(Text {
+      input_mode: InputMode::Code { tab_width: 4 },
+      ..Text::from(String::from("fn f() {\n    x\n}"))
+    })
+    .unwrap();
+
+    for c in "fn f() {\nx".chars() {
+      t.type_char(c);
+    }
+
+    assert_eq!(t.get_text_parts(), vec!["fn f() {\n    x", "\n}"]);
+    assert!((t.get_accuracy().unwrap() - 1.).abs() < 0.0001);
+
+    t.del_char();
+    t.del_char();
+    assert_eq!(t.get_text_parts(), vec!["fn f() {\n", "    x\n}"]);
+  }
+
+  #[test]
+  fn code_tab() {
+    let mut t = TextManager::new(Text {
+      input_mode: InputMode::Code { tab_width: 4 },
+      ..Text::from(String::from("a\n      b"))
+    })
+    .unwrap();
+
+    t.type_char('a');
+    t.type_char('\n');
+    t.del_char();
+    assert_eq!(t.get_text_parts(), vec!["a\n", "      b"]);
+
+    t.type_char('\t');
+    t.type_char('\t');
+    t.type_char('b');
+    assert_eq!(t.get_text_parts(), vec!["a\n      b", ""]);
+  }
+
+  #[test]
+  fn empty_text() {
+    assert!(matches!(
+      TextManager::new(String::new()),
+      Err(Error::EmptyText(_))
+    ));
+  }
+
+  #[test]
+  fn summary() {
+    let clock = MockClock::new();
+    let mut t = TextManager::with_clock(String::from("ab"), clock.clone()).unwrap();
+
+    t.type_char('a');
+    clock.advance(Duration::from_secs(1));
+    t.type_char('x');
+    t.del_char();
+    clock.advance(Duration::from_secs(1));
+    t.type_char('b');
+
+    let summary = t.get_summary();
+    assert_eq!(summary.accuracy, Some(1.));
+    assert_eq!(summary.skipped, 0);
+    assert_eq!(
+      summary.letters,
+      vec![
+        LetterStats {
+          letter: 'a',
+          count: 1,
+          average: Duration::from_secs(0),
+          errors: 0,
+        },
+        LetterStats {
+          letter: 'b',
+          count: 1,
+          average: Duration::from_secs(1),
+          errors: 1,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn speed() {
+    let clock = MockClock::new();
+    let mut t = TextManager::with_clock(String::from("one two three"), clock.clone()).unwrap();
+
+    assert_eq!(t.get_wpm(), None);
+    t.type_char('o');
+    assert_eq!((t.get_wpm(), t.get_cpm()), (None, None));
+    t.del_char();
+    for c in "one two ".chars() {
+      t.type_char(c);
+      clock.advance(Duration::from_millis(500));
+    }
+
+    // 8 characters and 2 words in 4 seconds.
+    assert!((t.get_cpm().unwrap() - 120.).abs() < 0.0001);
+    assert!((t.get_wpm().unwrap() - 30.).abs() < 0.0001);
+  }
+
+  #[test]
+  fn nothing_to_save() {
+    let mut t = TextManager::new(String::from("one")).unwrap();
+    assert!(!t.end_run().unwrap());
+
+    t.type_char('o');
+    t.del_char();
+    assert!(!t.end_run().unwrap());
+  }
+
+  #[test]
+  fn clock_set_back() {
+    let clock = MockClock::new();
+    let mut t = TextManager::with_clock(String::from("one"), clock.clone()).unwrap();
+
+    clock.set(Duration::from_secs(10));
+    t.type_char('o');
+    clock.set(Duration::from_secs(5));
+    t.type_char('n');
+
+    assert_eq!((t.get_wpm(), t.get_cpm()), (None, None));
+  }
+
+  #[test]
+  fn matches_full_rescan() {
+    let text = "Здравей, свят! Hello,\nworld и още.";
+    let expected: std::vec::Vec<char> = text.chars().collect();
+    let mut t = TextManager::new(String::from(text)).unwrap();
+    let mut typed: std::vec::Vec<char> = vec![];
+
+    let mut seed: u32 = 7;
+    for _ in 0..500 {
+      seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+      let roll = seed >> 16;
+      if roll.is_multiple_of(4) {
+        t.del_char();
+        typed.pop();
+      } else if typed.len() < expected.len() {
+        let c = if roll.is_multiple_of(5) {
+          'x'
+        } else {
+          expected[typed.len()]
+        };
+        t.type_char(c);
+        typed.push(c);
+      }
+
+      let mut parts = vec![String::new()];
+      let mut current_right = true;
+      for (c, e) in typed.iter().zip(&expected) {
+        if (c == e) != current_right {
+          parts.push(String::new());
+          current_right = !current_right;
+        }
+        parts.last_mut().unwrap().push(*e);
+      }
+      parts.push(expected[typed.len()..].iter().collect());
+      assert_eq!(t.get_text_parts(), parts);
+
+      let correct = typed.iter().zip(&expected).filter(|(c, e)| c == e).count();
+      assert_eq!(t.typed_chars as usize, correct);
+      assert_eq!(
+        t.get_column(),
+        typed.len() - expected[..typed.len()].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1)
+      );
+    }
+  }
 }
@@ -1,331 +1,696 @@
-use crate::categories;
-use crate::text::TextManager;
-use pancurses;
+use crate::backend::{Backend, Key, Style, Window};
+use typing_racer::history::{self, History};
+use typing_racer::{Layout, Level, Text, TextManager};
+use typing_racer::{Categories, Error, ErrorMode, InputMode, Result, Token, TokenKind};
+use unicode_segmentation::UnicodeSegmentation;
+
+// How often the live stats are redrawn while waiting for input.
+const TICK: std::time::Duration = std::time::Duration::from_millis(100);
+// How often the smooth caret moves while it catches up with the cursor.
+const FRAME: std::time::Duration = std::time::Duration::from_millis(16);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Caret {
+  #[default]
+  Block,
+  Underline,
+  // The terminal's own cursor, as a bar before the next char.
+  Bar,
+  // A block that glides over the text to the cursor instead of jumping.
+  Smooth,
+}
 
-const COLOR_NORMAL: i16 = 0;
-const COLOR_RIGHT: i16 = 1;
-const COLOR_WRONG: i16 = 2;
-const COLOR_OPTION_SELECTED: i16 = 3;
-const COLOR_CURRENT_CHAR: i16 = 3;
+impl Caret {
+  pub fn parse(value: &str) -> Option<Caret> {
+    match &value.trim().to_lowercase()[..] {
+      "block" => Some(Caret::Block),
+      "underline" => Some(Caret::Underline),
+      "bar" => Some(Caret::Bar),
+      "smooth" => Some(Caret::Smooth),
+      _ => None,
+    }
+  }
+}
+
+// How mistyped graphemes are shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Errors {
+  // The text as it should be, marked wrong.
+  #[default]
+  Expected,
+  // What was typed instead.
+  Typed,
+  // What was typed instead, with the text as it should be on a row under every line.
+  Below,
+}
+
+impl Errors {
+  pub fn parse(value: &str) -> Option<Errors> {
+    match &value.trim().to_lowercase()[..] {
+      "expected" => Some(Errors::Expected),
+      "typed" => Some(Errors::Typed),
+      "below" => Some(Errors::Below),
+      _ => None,
+    }
+  }
+}
+
+// Set from the command line.
+#[derive(Default)]
+pub struct Options {
+  // The text is laid out in a column at most this wide, in the middle of its window.
+  pub line_width: Option<usize>,
+  pub caret: Caret,
+  pub errors: Errors,
+  pub error_mode: ErrorMode,
+  // Space in the middle of a word skips to the next one.
+  pub skip_words: bool,
+}
+
+enum MenuAction {
+  Select(usize),
+  Expand(usize),
+  Back,
+}
 
 enum UIMode {
-  TYPE,
-  COMMAND,
+  Type,
+  Command,
 }
 
 pub struct UI {
-  main_window: pancurses::Window,
-  text_window: pancurses::Window,
-  info_window: pancurses::Window,
+  backend: Box<dyn Backend>,
+  text_window: Window,
+  info_window: Window,
   text_manager: TextManager,
+  highlighting: std::vec::Vec<Token>,
+  // Of the current text, for the current window width.
+  layout: Option<Layout>,
+  // Where the smooth caret is drawn, as a byte offset in the text.
+  caret: Option<usize>,
+  options: Options,
+  personal_best: Option<f32>,
   ui_mode: UIMode,
   is_first_update: bool,
-  categories: categories::Categories,
+  history: History,
+  categories: Categories,
   current_category: String,
+  // Errors since the last command, shown under the text info until the next one.
+  errors: std::cell::RefCell<std::vec::Vec<Error>>,
 }
 
 impl UI {
-  pub fn new(texts_dir: String) -> Self {
-    std::env::set_var("ESCDELAY", "0");
-
-    let main_window = pancurses::initscr();
-
-    main_window.keypad(true);
-    main_window.nodelay(true);
-
-    pancurses::noecho();
-    pancurses::start_color();
-    pancurses::nl();
-    pancurses::curs_set(0);
-
-    pancurses::init_pair(COLOR_NORMAL, pancurses::COLOR_WHITE, pancurses::COLOR_BLACK);
-    pancurses::init_pair(COLOR_RIGHT, pancurses::COLOR_GREEN, pancurses::COLOR_BLACK);
-    pancurses::init_pair(COLOR_WRONG, pancurses::COLOR_WHITE, pancurses::COLOR_RED);
-    pancurses::init_pair(
-      COLOR_OPTION_SELECTED,
-      pancurses::COLOR_BLACK,
-      pancurses::COLOR_WHITE,
-    );
-    pancurses::init_pair(
-      COLOR_CURRENT_CHAR,
-      pancurses::COLOR_BLACK,
-      pancurses::COLOR_WHITE,
-    );
-
-    let (text_window, info_window) = Self::create_subwindows(&main_window);
-    let categories = categories::Categories::new(texts_dir);
-
-    UI {
-      main_window,
+  pub fn new(texts_dir: String, options: Options) -> Result<Self> {
+    let history = History::default();
+    let categories = Categories::with_history(texts_dir, history.clone());
+    let text = categories.get_text("Basic");
+    let highlighting = text.highlight();
+    let mut text_manager = TextManager::new(text)?;
+    text_manager.set_history(history.clone());
+    text_manager.set_error_mode(options.error_mode);
+    text_manager.set_skip_words(options.skip_words);
+
+    let backend = crate::backend::open()?;
+    let (text_window, info_window) = Self::create_subwindows(backend.as_ref());
+
+    let mut ui = UI {
+      backend,
       text_window,
       info_window,
-      text_manager: TextManager::new(categories.get_text("Basic")),
-      ui_mode: UIMode::TYPE,
+      highlighting,
+      layout: None,
+      caret: None,
+      options,
+      personal_best: None,
+      text_manager,
+      ui_mode: UIMode::Type,
       is_first_update: true,
+      history,
       categories,
       current_category: String::from("Basic"),
-    }
+      errors: std::cell::RefCell::new(vec![]),
+    };
+    ui.personal_best = ui.load_personal_best();
+    ui.report_category_errors();
+    Ok(ui)
   }
 
   pub fn run(&mut self) {
     loop {
       match self.ui_mode {
-        UIMode::COMMAND => {
+        UIMode::Command => {
           if !self.command_loop() {
             break;
           }
           self.is_first_update = true;
         }
-        UIMode::TYPE => {
+        UIMode::Type => {
           self.type_loop();
         }
       }
       self.common_loop();
     }
-
-    pancurses::endwin();
   }
 
   fn end_run(&mut self) {
-    self.text_manager.end_run();
-
-    let (max_y, max_x) = self.main_window.get_max_yx();
-    let stats_window = pancurses::newwin(max_y, max_x, 0, 0);
-    stats_window.keypad(true);
-    stats_window.nodelay(true);
-
-    stats_window.mv(0, 0);
-    stats_window.addstr("Run saved! Press q to go back to typing.\n");
-
-    self.write_info_to_window(&stats_window);
-    stats_window.refresh();
+    let wpm = self.text_manager.get_wpm();
+    let saved = self.text_manager.end_run();
+    let is_personal_best = match (matches!(saved, Ok(true)), wpm, self.personal_best) {
+      (true, Some(wpm), Some(best)) => wpm > best,
+      (true, Some(_), None) => true,
+      _ => false,
+    };
+
+    self.show_results(&saved, is_personal_best);
     loop {
-      match stats_window.getch() {
-        Some(pancurses::Input::Character('q')) => {
-          self.ui_mode = UIMode::TYPE;
+      match self.backend.read_key(None) {
+        Some(Key::Char('q')) => {
+          self.ui_mode = UIMode::Type;
           break;
         }
-        Some(pancurses::Input::KeyDown) => {}
-        Some(pancurses::Input::KeyUp) => {}
+        Some(Key::Resize) => {
+          // The typing windows are remade too, for when the results are closed.
+          self.recreate_subwindows();
+          self.show_results(&saved, is_personal_best);
+        }
         _ => (),
       }
     }
-    stats_window.delwin();
-    self.text_manager = TextManager::new(self.categories.get_text(&self.current_category));
+    self.set_text(self.categories.get_text(&self.current_category));
+    if let Err(error) = saved {
+      self.report(error);
+    }
+  }
+
+  fn show_results(&mut self, saved: &Result<bool>, is_personal_best: bool) {
+    let (max_y, max_x) = self.backend.size();
+    let mut stats_window = Window::new(max_y, max_x, 0, 0);
+
+    match saved {
+      Ok(true) => stats_window.addstr("Run saved! Press q to go back to typing.\n"),
+      Ok(false) => {
+        stats_window.addstr("Nothing typed, run not saved. Press q to go back to typing.\n")
+      }
+      Err(error) => stats_window.addstr(format!(
+        "Run not saved, {}. Press q to go back to typing.\n",
+        error
+      )),
+    };
+    if is_personal_best {
+      stats_window.addstr(match self.personal_best {
+        Some(best) => format!("New PB! Previous best on this text: {:.2} WPM\n", best),
+        None => String::from("New PB! First run on this text.\n"),
+      });
+    }
+
+    self.write_info_to_window(&mut stats_window);
+    self.backend.draw(&stats_window);
   }
 
-  fn show_improvement(&mut self) -> pancurses::Window {
-    let (max_y, max_x) = self.main_window.get_max_yx();
-    let stats_window = pancurses::newwin(max_y, max_x, 0, 0);
-    stats_window.keypad(true);
-    stats_window.nodelay(true);
-    stats_window.mv(0, 0);
+  fn show_improvement(&mut self) {
+    let (max_y, max_x) = self.backend.size();
+    let mut stats_window = Window::new(max_y, max_x, 0, 0);
 
     let data = self
       .text_manager
       .get_improvement(max_x as usize, (max_y - 2) as usize);
-    if let Some(data) = data {
-      stats_window.addstr("Improvement: \n");
-      let mut i = 0;
-      for point in data {
-        stats_window.mvaddch(max_y - 1 - point as i32, i, '*');
-        i += 1;
+    match data {
+      Ok(Some(data)) => {
+        stats_window.addstr("Improvement: \n");
+        for (i, point) in data.into_iter().enumerate() {
+          stats_window.mvaddch(max_y - 1 - point as i32, i as i32, '*');
+        }
+      }
+      Ok(None) => {
+        stats_window.addstr("No improvement data found\n");
+      }
+      Err(error) => {
+        stats_window.addstr(format!("No improvement data, {}\n", error));
       }
-    } else {
-      stats_window.addstr("No improvement data found\n");
     }
-    stats_window.refresh();
-    return stats_window;
+    self.backend.draw(&stats_window);
   }
 
   fn command_loop(&mut self) -> bool {
-    match self.main_window.getch() {
-      Some(pancurses::Input::Character('i')) => {
-        self.ui_mode = UIMode::TYPE;
+    let input = self.backend.read_key(Some(TICK));
+    if input.is_some() {
+      self.errors.borrow_mut().clear();
+    }
+    match input {
+      Some(Key::Char('i')) => {
+        self.ui_mode = UIMode::Type;
       }
-      Some(pancurses::Input::Character('q')) => {
+      Some(Key::Char('q')) => {
         return false;
       }
-      Some(pancurses::Input::Character('c')) => {
-        let categories = self.categories.get_categories();
-        if categories.len() > 0 {
-          let idx = self.menu_choose(&categories);
-          self.current_category = categories[idx].clone();
-          self.text_manager = TextManager::new(self.categories.get_text(&self.current_category));
-          self.ui_mode = UIMode::TYPE;
+      Some(Key::Char('c')) => {
+        while let Some(category) = self.choose_category() {
+          self.current_category = category;
+          if let Some(text) = self.choose_text() {
+            self.set_text(text);
+            break;
+          }
         }
+        self.ui_mode = UIMode::Type;
       }
-      Some(pancurses::Input::Character('t')) => {
-        let mut improvement_win : Option<pancurses::Window> = None;
-        let mut update_improvement = true;
+      Some(Key::Char('t')) => {
+        self.show_improvement();
         loop {
-          match self.main_window.getch() {
-            Some(pancurses::Input::Character('q')) => {
-              self.ui_mode = UIMode::TYPE;
+          match self.backend.read_key(None) {
+            Some(Key::Char('q')) => {
+              self.ui_mode = UIMode::Type;
               break;
             }
-            Some(pancurses::Input::KeyResize) => {
-              update_improvement = true;
+            Some(Key::Resize) => {
+              self.show_improvement();
             }
-            _ => {
-              if update_improvement {
-                if let Some(win) = improvement_win {
-                  win.delwin();
-                }
-                improvement_win = Some(self.show_improvement());
-                update_improvement = false;
-              }
-            },
+            _ => (),
           }
         }
-        if let Some(win) = improvement_win {
-          win.delwin();
-        }
       }
-      Some(pancurses::Input::Character('e')) => {
+      Some(Key::Char('e')) => {
         self.end_run();
       }
       _ => (),
     }
-    return true;
+    true
   }
 
-  fn menu_choose(&self, list: &std::vec::Vec<String>) -> usize {
-    let (max_y, max_x) = self.main_window.get_max_yx();
-    let menu_window = pancurses::newwin(max_y, max_x, 0, 0);
-    menu_window.keypad(true);
-    menu_window.nodelay(true);
-    let mut curr = 0;
+  // Keeps the current text if the new one cannot be typed.
+  fn set_text(&mut self, text: Text) {
+    self.report_category_errors();
+    let highlighting = text.highlight();
+    match TextManager::new(text) {
+      Ok(mut text_manager) => {
+        text_manager.set_history(self.history.clone());
+        text_manager.set_error_mode(self.options.error_mode);
+        text_manager.set_skip_words(self.options.skip_words);
+        self.highlighting = highlighting;
+        self.text_manager = text_manager;
+        self.layout = None;
+        self.caret = None;
+        self.personal_best = self.load_personal_best();
+      }
+      Err(error) => self.report(error),
+    }
+  }
+
+  fn report(&self, error: Error) {
+    self.errors.borrow_mut().push(error);
+  }
+
+  fn report_category_errors(&self) {
+    self.errors.borrow_mut().extend(self.categories.take_errors());
+  }
+
+  fn load_history(&self) -> std::vec::Vec<history::Record> {
+    self.history.load().unwrap_or_else(|error| {
+      self.report(error);
+      vec![]
+    })
+  }
+
+  fn load_personal_best(&self) -> Option<f32> {
+    let records = self.load_history();
+    history::best_wpm_by(&records, "id")
+      .get(self.text_manager.get_text_id())
+      .copied()
+  }
+
+  fn choose_category(&mut self) -> Option<String> {
+    let mut path = std::path::PathBuf::new();
     loop {
-      match menu_window.getch() {
-        Some(pancurses::Input::Character('\n')) => {
-          break;
+      let categories = if path.as_os_str().is_empty() {
+        self.categories.get_categories()
+      } else {
+        self.categories.get_subcategories(&path.to_string_lossy())
+      };
+      if categories.is_empty() {
+        if !path.pop() {
+          return None;
         }
-        Some(pancurses::Input::KeyDown) => {
-          curr = (curr + 1) % list.len();
+        continue;
+      }
+
+      let options: std::vec::Vec<String> = categories
+        .iter()
+        .map(|category| {
+          let name = std::path::Path::new(category)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+          if self.categories.get_subcategories(category).is_empty() {
+            name
+          } else {
+            format!("{}/", name)
+          }
+        })
+        .collect();
+
+      let title = format!("Categories: /{}", path.to_string_lossy());
+      match self.menu_choose(&title, &options) {
+        MenuAction::Select(idx) => return Some(categories[idx].clone()),
+        MenuAction::Expand(idx) => {
+          if !self.categories.get_subcategories(&categories[idx]).is_empty() {
+            path = std::path::PathBuf::from(&categories[idx]);
+          }
         }
-        Some(pancurses::Input::KeyUp) => {
-          curr = if curr == 0 { list.len() - 1 } else { curr - 1 };
+        MenuAction::Back => {
+          if !path.pop() {
+            return None;
+          }
         }
-        _ => (),
       }
-      menu_window.mv(0, 0);
-      menu_window
-        .addstr("Press Up and Down to choose an option. Press Enter to make a selection.\n");
-      for i in 0..list.len() {
+    }
+  }
+
+  fn choose_text(&mut self) -> Option<Text> {
+    const RANDOM: usize = 0;
+    const RANDOM_EASY: usize = 1;
+    const RANDOM_MEDIUM: usize = 2;
+    const RANDOM_HARD: usize = 3;
+    const NEXT_UNPLAYED: usize = 4;
+    const RETRY: usize = 5;
+    const SORT: usize = 6;
+    const ORDERS: [&str; 3] = ["name", "difficulty", "length"];
+
+    let mut texts = self.categories.list_texts(&self.current_category);
+    let best = history::best_wpm_by(&self.load_history(), "id");
+    let mut order = 0;
+    // Why the last choice gave no text.
+    let mut notice = None;
+
+    // Picks by difficulty are made here, so that the menu stays up if there is nothing to pick.
+    let idx = loop {
+      let mut options = vec![
+        String::from("Random from this subtree"),
+        String::from("Random easy"),
+        String::from("Random medium"),
+        String::from("Random hard"),
+        String::from("Next unplayed"),
+        String::from("Retry same text"),
+        format!("Sort by: {}", ORDERS[order]),
+      ];
+      for text in &texts {
+        // A text split into passages shows its best on any of them.
+        let text_best = text.ids.iter().filter_map(|id| best.get(id)).copied().reduce(f32::max);
+        let best = match text_best {
+          Some(wpm) => format!("best {:.2} WPM", wpm),
+          None => String::from("not played"),
+        };
+        let level = Level::from_score(text.difficulty).name();
+        options.push(format!(
+          "{: <24} {: >7} chars  {: >3.0} {: <6}  {}",
+          text.file_name, text.length, text.difficulty, level, best
+        ));
+      }
+
+      let title = match &notice {
+        Some(notice) => format!("Texts in {} ({})", self.current_category, notice),
+        None => format!("Texts in {}", self.current_category),
+      };
+      match self.menu_choose(&title, &options) {
+        MenuAction::Select(SORT) | MenuAction::Expand(SORT) => {
+          order = (order + 1) % ORDERS.len();
+          match ORDERS[order] {
+            "difficulty" => texts.sort_by(|a, b| a.difficulty.total_cmp(&b.difficulty)),
+            "length" => texts.sort_by_key(|text| text.length),
+            _ => texts.sort_by(|a, b| a.name.cmp(&b.name)),
+          }
+        }
+        MenuAction::Select(idx) | MenuAction::Expand(idx) => {
+          let level = match idx {
+            RANDOM_EASY => Level::Easy,
+            RANDOM_MEDIUM => Level::Medium,
+            RANDOM_HARD => Level::Hard,
+            _ => break idx,
+          };
+          match self
+            .categories
+            .get_text_with_difficulty(&self.current_category, level)
+          {
+            Ok(text) => return Some(text),
+            Err(error) => notice = Some(error.to_string()),
+          }
+        }
+        MenuAction::Back => return None,
+      }
+    };
+    Some(match idx {
+      RANDOM => self.categories.get_text(&self.current_category),
+      NEXT_UNPLAYED => self
+        .categories
+        .get_next_unplayed(&self.current_category)
+        .unwrap_or_else(|| self.categories.get_text(&self.current_category)),
+      RETRY => self.text_manager.to_text(),
+      idx => self
+        .categories
+        .get_text_by_name(&texts[idx - SORT - 1].name)
+        .unwrap_or_else(|error| {
+          self.report(error);
+          self.categories.get_text(&self.current_category)
+        }),
+    })
+  }
+
+  fn menu_choose(&mut self, title: &str, list: &[String]) -> MenuAction {
+    let mut curr = 0;
+    let mut first = 0;
+    loop {
+      let (max_y, max_x) = self.backend.size();
+      let mut menu_window = Window::new(max_y, max_x, 0, 0);
+      menu_window.addstr(title);
+      menu_window.addstr(
+        "\nUp/Down: choose, Enter: select, Right: open subcategories, Left/Esc: go back.\n",
+      );
+      let visible = (max_y as usize).saturating_sub(2).max(1);
+      if curr < first {
+        first = curr;
+      } else if curr >= first + visible {
+        first = curr + 1 - visible;
+      }
+      for (i, option) in list.iter().enumerate().skip(first).take(visible) {
         if i == curr {
-          menu_window.color_set(COLOR_OPTION_SELECTED);
+          menu_window.color_set(Style::Selected);
         }
-        menu_window.addstr(&list[i][..]);
+        menu_window.addnstr(option, (max_x as usize).saturating_sub(1));
+        menu_window.color_set(Style::Normal);
         menu_window.addch('\n');
-        menu_window.color_set(COLOR_NORMAL);
       }
-    }
-    menu_window.delwin();
+      self.backend.draw(&menu_window);
 
-    //self.main_window.touch();
-    //self.main_window.refresh();
-
-    return curr;
+      match self.backend.read_key(None) {
+        Some(Key::Char('\n')) => {
+          return MenuAction::Select(curr);
+        }
+        Some(Key::Right) => {
+          return MenuAction::Expand(curr);
+        }
+        Some(Key::Left) | Some(Key::Escape) => {
+          return MenuAction::Back;
+        }
+        Some(Key::Down) => {
+          curr = (curr + 1) % list.len();
+        }
+        Some(Key::Up) => {
+          curr = if curr == 0 { list.len() - 1 } else { curr - 1 };
+        }
+        _ => (),
+      }
+    }
   }
 
-  fn create_subwindows(main_window: &pancurses::Window) -> (pancurses::Window, pancurses::Window) {
-    let (max_y, max_x) = main_window.get_max_yx();
+  fn create_subwindows(backend: &dyn Backend) -> (Window, Window) {
+    let (max_y, max_x) = backend.size();
 
     let text_w = max_x / 3 * 2;
 
-    let text_window = main_window.subwin(max_y, text_w, 0, 0).unwrap();
-    text_window.setscrreg(0, max_y);
+    let text_window = Window::new(max_y, text_w, 0, 0);
 
-    let info_window = main_window
-      .subwin(max_y, max_x - text_w, 0, text_w)
-      .unwrap();
+    let info_window = Window::new(max_y, max_x - text_w, 0, text_w);
 
-    return (text_window, info_window);
+    (text_window, info_window)
   }
 
   fn recreate_subwindows(&mut self) {
-    let (new_text_window, new_info_window) = Self::create_subwindows(&self.main_window);
-    std::mem::replace(&mut self.text_window, new_text_window).delwin();
-    std::mem::replace(&mut self.info_window, new_info_window).delwin();
-    self.main_window.clear();
-    self.text_window.clear();
-    self.info_window.clear();
-    self.main_window.refresh();
-    self.text_window.refresh();
-    self.info_window.refresh();
+    let (text_window, info_window) = Self::create_subwindows(self.backend.as_ref());
+    self.text_window = text_window;
+    self.info_window = info_window;
+    self.layout = None;
+    self.backend.clear();
     self.is_first_update = true;
   }
 
   fn type_loop(&mut self) {
-    let mut need_to_update_text = self.is_first_update;
-    match self.main_window.getch() {
-      Some(pancurses::Input::Character('\u{1b}')) => {
-        self.ui_mode = UIMode::COMMAND;
+    let gliding = self.caret.is_some_and(|caret| caret != self.text_manager.get_cursor());
+    let mut need_to_update_text = self.is_first_update || gliding;
+    match self.backend.read_key(Some(if gliding { FRAME } else { TICK })) {
+      Some(Key::Escape) => {
+        self.backend.set_cursor(None);
+        self.ui_mode = UIMode::Command;
       }
-      Some(pancurses::Input::Character(c)) => {
+      Some(Key::Char(c)) => {
         self.text_manager.type_char(c);
         need_to_update_text = true;
       }
-      Some(pancurses::Input::KeyBackspace) => {
+      Some(Key::Backspace) => {
         self.text_manager.del_char();
         need_to_update_text = true;
       }
-      Some(pancurses::Input::KeyResize) => {
+      Some(Key::DeleteWord) => {
+        self.text_manager.del_word();
+        need_to_update_text = true;
+      }
+      Some(Key::Resize) => {
         self.recreate_subwindows();
       }
       _ => (),
     }
     if need_to_update_text {
-      let parts = self.text_manager.get_text_parts();
-      let (h, _) = self.text_window.get_max_yx();
-      self.text_window.scrollok(true);
-      self.text_window.mv(0, 0);
-      let mut current_right = true;
-      for _ in 0..h / 2 {
-        self.text_window.addch('\n');
-      }
-      for i in 0..parts.len() {
-        let current_rest = i == parts.len() - 1;
-        if current_rest {
-          self.text_window.color_set(COLOR_NORMAL);
-        } else if current_right {
-          self.text_window.color_set(COLOR_RIGHT);
-          current_right = false;
-        } else {
-          self.text_window.color_set(COLOR_WRONG);
-          current_right = true;
-        }
-        let mut lines = 0;
-        let mut last_x = 0;
-
-        for (i, c) in parts[i].char_indices() {
-          let (_, x) = self.text_window.get_cur_yx();
-          if x == 0 && last_x != 0 {
-            lines += 1;
-          }
-          if current_rest && lines >= h / 2 {
-            break;
-          }
-          if current_rest && i == 0 {
-            self.text_window.color_set(COLOR_CURRENT_CHAR);
-          }
+      self.draw_text();
+      self.is_first_update = false;
+    }
+  }
 
-          // self.text_window.addch(c);
-          self.text_window.addstr(c.to_string()); // workaround for unicode characters
+  // Moves the smooth caret a third of the way to the cursor, and at least a grapheme.
+  fn glide_caret(&mut self, cursor: usize) -> usize {
+    let text = self.text_manager.get_text();
+    let caret = self.caret.unwrap_or(cursor).min(text.len());
+    let caret = if caret < cursor {
+      let graphemes = text[caret..cursor].graphemes(true);
+      let step = graphemes.clone().count().div_ceil(3);
+      caret + graphemes.take(step).map(str::len).sum::<usize>()
+    } else {
+      let graphemes = text[cursor..caret].graphemes(true).rev();
+      let step = graphemes.clone().count().div_ceil(3);
+      caret - graphemes.take(step).map(str::len).sum::<usize>()
+    };
+    self.caret = Some(caret);
+    caret
+  }
 
-          last_x = x;
-          if current_rest {
-            self.text_window.color_set(COLOR_NORMAL);
+  // Draws the lines around the current one, which stays in the middle of the window.
+  fn draw_text(&mut self) {
+    let cursor = self.text_manager.get_cursor();
+    let caret = match self.options.caret {
+      Caret::Smooth => self.glide_caret(cursor),
+      _ => cursor,
+    };
+    let caret_style = match self.options.caret {
+      Caret::Block | Caret::Smooth => Some(Style::CurrentChar),
+      Caret::Underline => Some(Style::Underlined),
+      Caret::Bar => None,
+    };
+    let (h, w) = self.text_window.get_max_yx();
+    let width = self
+      .options
+      .line_width
+      .map_or(w, |line_width| w.min(line_width as i32))
+      .max(1);
+    let margin = (w - width) / 2;
+    let text = self.text_manager.get_text();
+    if self.layout.as_ref().map(Layout::width) != Some(width as usize) {
+      self.layout = Some(Layout::new(text, width as usize));
+    }
+    let layout = match &self.layout {
+      Some(layout) => layout,
+      None => return,
+    };
+
+    let rows_per_line = if self.options.errors == Errors::Below { 2 } else { 1 };
+    let slots = (h / rows_per_line).max(1) as usize;
+    let current = layout.line_at(cursor);
+    let first = current.saturating_sub(slots / 2);
+    let end = layout.lines().len().min(current + slots - slots / 2);
+    let lines = &layout.lines()[first..end];
+
+    for row in 0..h {
+      self.text_window.mv(row, 0);
+      self.text_window.clrtoeol();
+    }
+    let mut caret_position = None;
+    for (i, line) in lines.iter().enumerate() {
+      let row = ((first + i) as i32 - current as i32 + (slots / 2) as i32) * rows_per_line;
+      let mut column = 0;
+      for aligned in self.text_manager.get_aligned(line.start..line.end) {
+        let style = match (caret_style, aligned.typed) {
+          (Some(style), _) if aligned.offset == caret => style,
+          (_, Some(_)) if aligned.is_wrong() => Style::Wrong,
+          (_, Some(_)) => Style::Right,
+          _ => Self::token_color(TokenKind::at(&self.highlighting, aligned.offset)),
+        };
+        let width = match layout.grapheme_width(aligned.expected, column) {
+          // Whitespace hanging past the end of a line is only drawn under the caret, if there
+          // is room for it.
+          0 if aligned.offset == caret && margin + (column as i32) < w => 1,
+          width => width,
+        };
+        let shown = match aligned.typed {
+          // What was typed instead is only shown if it fits where the expected grapheme was.
+          // Skipped graphemes have nothing typed for them.
+          Some(typed)
+            if style == Style::Wrong
+              && self.options.errors != Errors::Expected
+              && !typed.is_empty()
+              && layout.grapheme_width(typed, column) == width
+              && !typed.chars().any(char::is_control) =>
+          {
+            typed
           }
+          _ => aligned.expected,
+        };
+
+        self.text_window.mv(row, margin + column as i32);
+        if aligned.offset == caret {
+          caret_position = Some((row, margin + column as i32));
         }
+        self.text_window.color_set(style);
+        Self::draw_grapheme(&mut self.text_window, shown, width);
+        if rows_per_line == 2 && style == Style::Wrong {
+          self.text_window.mv(row + 1, margin + column as i32);
+          self.text_window.color_set(Style::Right);
+          Self::draw_grapheme(&mut self.text_window, aligned.expected, width);
+        }
+        column += width;
       }
-      self.text_window.refresh();
-      self.is_first_update = false;
+      if line.end == text.len() && caret == text.len() {
+        caret_position = Some((row, margin + column as i32));
+      }
+    }
+    self.text_window.color_set(Style::Normal);
+    self.backend.draw(&self.text_window);
+
+    if self.options.caret == Caret::Bar {
+      let (y, x) = self.text_window.get_beg_yx();
+      self
+        .backend
+        .set_cursor(caret_position.map(|(row, column)| (y + row, x + column)));
+    }
+  }
+
+  // Whitespace is drawn as blanks, as wide as it is laid out.
+  fn draw_grapheme(window: &mut Window, grapheme: &str, width: usize) {
+    if grapheme.chars().all(char::is_whitespace) {
+      for _ in 0..width {
+        window.addch(' ');
+      }
+    } else {
+      window.add_grapheme(grapheme);
+    }
+  }
+
+  fn token_color(kind: TokenKind) -> Style {
+    match kind {
+      TokenKind::Plain => Style::Normal,
+      TokenKind::Keyword => Style::Keyword,
+      TokenKind::String => Style::String,
+      TokenKind::Comment => Style::Comment,
+      TokenKind::Number => Style::Number,
     }
   }
 
-  fn write_info_to_window(&self, window: &pancurses::Window) {
+  fn write_info_to_window(&self, window: &mut Window) {
+    self.write_header_to_window(window);
     window.addstr(format!(
       "  Accuracy: {:.2}%\n",
       self.text_manager.get_accuracy().unwrap_or(0.) * 100.
@@ -338,6 +703,9 @@ impl UI {
       "  CPM: {:.2}\n",
       self.text_manager.get_cpm().unwrap_or(0.)
     ));
+    if self.text_manager.get_skipped() > 0 {
+      window.addstr(format!("  Skipped: {}\n", self.text_manager.get_skipped()));
+    }
     window.addstr("  Slowest letters:  Most error letters:\n");
 
     let slowest_letters = self.text_manager.get_slowest_letters();
@@ -362,12 +730,69 @@ impl UI {
         break;
       }
     }
+  }
+
+  fn write_header_to_window(&self, window: &mut Window) {
+    let metadata = self.text_manager.get_metadata();
+    let name = self.text_manager.get_text_name();
+    let title = metadata.title.as_deref().unwrap_or(if name.is_empty() {
+      "Untitled"
+    } else {
+      name
+    });
+
+    let mut lines = vec![match self.text_manager.get_passage() {
+      Some(passage) => format!("  {} ({}/{})", title, passage.index + 1, passage.count),
+      None => format!("  {}", title),
+    }];
+    if let Some(author) = &metadata.author {
+      lines.push(format!("  by {}", author));
+    }
+    if let Some(source) = &metadata.source {
+      lines.push(format!("  {}", source));
+    }
+    let mut details = vec![];
+    if let InputMode::Code { .. } = self.text_manager.get_input_mode() {
+      details.push(String::from("code"));
+    }
+    if let Some(language) = &metadata.language {
+      details.push(language.clone());
+    }
+    if let Some(difficulty) = &metadata.difficulty {
+      details.push(difficulty.clone());
+    }
+    details.extend(metadata.tags.iter().cloned());
+    if !details.is_empty() {
+      lines.push(format!("  [{}]", details.join(", ")));
+    }
+
+    lines.push(match self.personal_best {
+      Some(best) => format!("  Personal best: {:.2} WPM", best),
+      None => String::from("  Personal best: none yet"),
+    });
+
+    let errors = self.errors.borrow();
+    if let Some(error) = errors.first() {
+      lines.push(format!("  ! {}", error));
+    }
+    if errors.len() > 1 {
+      lines.push(format!("  ! and {} more", errors.len() - 1));
+    }
 
-    window.refresh();
+    for line in lines {
+      window.addstr(line);
+      window.clrtoeol();
+      window.addch('\n');
+    }
+    window.clrtoeol();
+    window.addch('\n');
   }
 
   fn common_loop(&mut self) {
     self.info_window.mv(0, 0);
-    self.write_info_to_window(&self.info_window);
+    let mut info_window = std::mem::take(&mut self.info_window);
+    self.write_info_to_window(&mut info_window);
+    self.backend.draw(&info_window);
+    self.info_window = info_window;
   }
 }